@@ -0,0 +1,358 @@
+//! Represents datetimes, dates, times and decimals with the logical types of Avro.
+//!
+//! The Avro format enables the logical types with [`with_logical_types`] while it
+//! runs the encoder and the decoder, on top of the positional encoding. The unions
+//! are encoded as the Avro unions, where `null` is the first branch if allowed.
+
+use crate::{
+    attr::AttrStr,
+    cbor,
+    decode::primitive::BytesVisitor,
+    encode::variant_index,
+    error::{self, Convert, ErrorKind},
+    import::import,
+    object::{Object, ObjectRef},
+    positional,
+    schema::{Schema, Union},
+};
+use serde::{
+    de::{DeserializeSeed, Deserializer, SeqAccess, Visitor},
+    ser::{self, SerializeTuple, Serializer},
+    Deserialize,
+};
+use std::{cell::Cell, fmt};
+
+/// The precision of decimals, which is the same as `NUMERIC` of BigQuery.
+pub const DECIMAL_PRECISION: u32 = 38;
+/// The scale of decimals, i.e. the number of the digits after the decimal point.
+pub const DECIMAL_SCALE: u32 = 9;
+
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_DAY: i64 = 86_400 * MICROS_PER_SECOND;
+
+lazy_static::lazy_static! {
+    static ref ATTR_YEAR: AttrStr = AttrStr::new("year");
+    static ref ATTR_MONTH: AttrStr = AttrStr::new("month");
+    static ref ATTR_DAY: AttrStr = AttrStr::new("day");
+    static ref ATTR_HOUR: AttrStr = AttrStr::new("hour");
+    static ref ATTR_MINUTE: AttrStr = AttrStr::new("minute");
+    static ref ATTR_SECOND: AttrStr = AttrStr::new("second");
+    static ref ATTR_MICROSECOND: AttrStr = AttrStr::new("microsecond");
+    static ref ATTR_DAYS: AttrStr = AttrStr::new("days");
+    static ref ATTR_SECONDS: AttrStr = AttrStr::new("seconds");
+    static ref ATTR_MICROSECONDS: AttrStr = AttrStr::new("microseconds");
+    static ref ATTR_UTCOFFSET: AttrStr = AttrStr::new("utcoffset");
+    static ref ATTR_TZINFO: AttrStr = AttrStr::new("tzinfo");
+    static ref ATTR_UTC: AttrStr = AttrStr::new("utc");
+    static ref ATTR_MODULE: AttrStr = AttrStr::new("__module__");
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = Cell::new(false);
+}
+
+/// Runs `f` with the logical types and the positional encoding enabled.
+pub fn with_logical_types<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let enabled = replace(true);
+    let res = positional::with_positional(f);
+    replace(enabled);
+    res
+}
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+/// Sets the mode and returns the previous one.
+pub(crate) fn replace(enabled: bool) -> bool {
+    ENABLED.with(|e| e.replace(enabled))
+}
+
+/// Checks if the type can be encoded in Avro, so that the encoder and the decoder
+/// reject the types which have no Avro schema, e.g. literals and maps with non-string keys.
+pub fn check(schema: &Schema) -> error::Result<()> {
+    match schema {
+        Schema::Literal(_) => Err(err!("`{}` is not supported in Avro", schema.name())
+            .with_kind(ErrorKind::UnsupportedType)),
+        // The keys of untyped dicts are checked by `check_key`.
+        Schema::Dict(d) => match d.key.resolved()? {
+            Schema::Str | Schema::Any(_) => Ok(()),
+            s => bail!("the keys of maps must be `str` in Avro, got `{}`", s.name()),
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Checks if the key of a map is a string.
+pub(crate) fn check_key(key: &ObjectRef) -> error::Result<()> {
+    if !key.is_str_object() {
+        bail!(
+            "the keys of maps must be `str` in Avro, got `{}`",
+            key.typename()
+        );
+    }
+    Ok(())
+}
+
+/// Returns the module defining a class or an enum, which is the namespace of its Avro name.
+pub fn namespace(ty: &ObjectRef) -> error::Result<String> {
+    Ok(ty.get_attr(&ATTR_MODULE)?.as_str()?.into())
+}
+
+fn get_i64(object: &ObjectRef, attr: &AttrStr) -> error::Result<i64> {
+    object.get_attr(attr)?.as_i64()
+}
+
+/// Returns the number of days since 1970-01-01 in the proleptic Gregorian calendar.
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Returns the year, the month and the day of the number of days since 1970-01-01.
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719_468;
+    let era = if z >= 0 { z } else { z - 146_096 } / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400;
+    (if m <= 2 { y + 1 } else { y }, m, d)
+}
+
+fn days(object: &ObjectRef) -> error::Result<i64> {
+    Ok(days_from_civil(
+        get_i64(object, &ATTR_YEAR)?,
+        get_i64(object, &ATTR_MONTH)?,
+        get_i64(object, &ATTR_DAY)?,
+    ))
+}
+
+fn time_micros(object: &ObjectRef) -> error::Result<i64> {
+    let seconds = get_i64(object, &ATTR_HOUR)? * 3600
+        + get_i64(object, &ATTR_MINUTE)? * 60
+        + get_i64(object, &ATTR_SECOND)?;
+    Ok(seconds * MICROS_PER_SECOND + get_i64(object, &ATTR_MICROSECOND)?)
+}
+
+/// Returns the microseconds since the epoch. Naive datetimes are assumed to be in UTC.
+fn timestamp_micros(object: &ObjectRef) -> error::Result<i64> {
+    let offset = object.get_attr(&ATTR_UTCOFFSET)?.call0()?;
+    let offset = if offset.is_none() {
+        0
+    } else {
+        (get_i64(&offset, &ATTR_DAYS)? * 86_400 + get_i64(&offset, &ATTR_SECONDS)?)
+            * MICROS_PER_SECOND
+            + get_i64(&offset, &ATTR_MICROSECONDS)?
+    };
+    Ok(days(object)? * MICROS_PER_DAY + time_micros(object)? - offset)
+}
+
+fn time_args(micros: i64) -> error::Result<Vec<Object>> {
+    let seconds = micros / MICROS_PER_SECOND;
+    Ok(vec![
+        Object::new_i64(seconds / 3600)?,
+        Object::new_i64(seconds / 60 % 60)?,
+        Object::new_i64(seconds % 60)?,
+        Object::new_i64(micros % MICROS_PER_SECOND)?,
+    ])
+}
+
+fn date_args(days: i64) -> error::Result<Vec<Object>> {
+    let (y, m, d) = civil_from_days(days);
+    Ok(vec![
+        Object::new_i64(y)?,
+        Object::new_i64(m)?,
+        Object::new_i64(d)?,
+    ])
+}
+
+fn datetime_from_micros(micros: i64) -> error::Result<Object> {
+    let import = import()?;
+    let mut args = date_args(micros.div_euclid(MICROS_PER_DAY))?;
+    args.extend(time_args(micros.rem_euclid(MICROS_PER_DAY))?);
+    let utc = import.timezone.get_attr(&ATTR_UTC)?;
+    import
+        .datetime
+        .call_with_kwargs(args, vec![(&ATTR_TZINFO, utc)])
+}
+
+fn time_from_micros(micros: i64) -> error::Result<Object> {
+    if !(0..MICROS_PER_DAY).contains(&micros) {
+        bail!("time `{}` is out of range", micros);
+    }
+    import()?.time.call(time_args(micros)?)
+}
+
+/// Returns the unscaled value of a decimal in the big-endian two's-complement.
+fn decimal_bytes(object: &ObjectRef) -> error::Result<Vec<u8>> {
+    let text = object.to_str()?;
+    let text = text.as_str()?;
+    let (exponent, mantissa) = cbor::decimal_fraction(text).ok_or_else(|| {
+        err!("cannot encode decimal `{}`", text).with_kind(ErrorKind::InvalidValue)
+    })?;
+
+    let shift = exponent + DECIMAL_SCALE as i64;
+    let unscaled = if shift >= 0 {
+        10i128
+            .checked_pow(shift as u32)
+            .and_then(|p| mantissa.checked_mul(p))
+    } else {
+        10i128.checked_pow(-shift as u32).and_then(|p| {
+            if mantissa % p == 0 {
+                Some(mantissa / p)
+            } else {
+                None
+            }
+        })
+    };
+    let unscaled = match unscaled {
+        Some(v) if v.abs() < 10i128.pow(DECIMAL_PRECISION) => v,
+        _ => {
+            return Err(err!(
+                "decimal `{}` doesn't fit in precision {} and scale {}",
+                text,
+                DECIMAL_PRECISION,
+                DECIMAL_SCALE
+            )
+            .with_kind(ErrorKind::InvalidValue))
+        }
+    };
+
+    // Strip the redundant sign extension.
+    let bytes = unscaled.to_be_bytes();
+    let start = (0..bytes.len() - 1)
+        .find(|&i| {
+            !(bytes[i] == 0x00 && bytes[i + 1] & 0x80 == 0
+                || bytes[i] == 0xff && bytes[i + 1] & 0x80 != 0)
+        })
+        .unwrap_or(bytes.len() - 1);
+    Ok(bytes[start..].to_vec())
+}
+
+fn decimal_from_bytes(bytes: &[u8]) -> error::Result<Object> {
+    if bytes.is_empty() || bytes.len() > 16 {
+        bail!("invalid length of decimal: {}", bytes.len());
+    }
+    let init = if bytes[0] & 0x80 != 0 { -1i128 } else { 0 };
+    let mut mantissa = bytes.iter().fold(init, |v, b| v << 8 | *b as i128);
+    let mut exponent = -(DECIMAL_SCALE as i64);
+    while exponent < 0 && mantissa % 10 == 0 {
+        mantissa /= 10;
+        exponent += 1;
+    }
+    cbor::decimal_from_fraction(exponent, mantissa)
+}
+
+/// Serializes a datetime, date, time or decimal.
+pub(crate) fn serialize<S>(schema: &Schema, object: &ObjectRef, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match schema {
+        Schema::DateTime => s.serialize_i64(timestamp_micros(object).ser()?),
+        Schema::Date => s.serialize_i32(days(object).ser()? as i32),
+        Schema::Time => s.serialize_i64(time_micros(object).ser()?),
+        Schema::Decimal => s.serialize_bytes(&decimal_bytes(object).ser()?),
+        _ => Err(ser::Error::custom(format_args!(
+            "`{}` has no logical type",
+            schema.name()
+        ))),
+    }
+}
+
+/// Deserializes a datetime, date, time or decimal.
+pub(crate) fn deserialize<'de, D>(schema: &Schema, deserializer: D) -> Result<Object, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let obj = match schema {
+        Schema::DateTime => datetime_from_micros(i64::deserialize(deserializer)?),
+        Schema::Date => {
+            let days = i32::deserialize(deserializer)?;
+            date_args(days as i64).and_then(|args| import()?.date.call(args))
+        }
+        Schema::Time => time_from_micros(i64::deserialize(deserializer)?),
+        Schema::Decimal => {
+            let bytes = deserializer.deserialize_bytes(BytesVisitor(false))?;
+            decimal_from_bytes(bytes.as_bytes().de()?)
+        }
+        _ => Err(err!("`{}` has no logical type", schema.name())),
+    };
+    obj.de()
+}
+
+/// Serializes a union as the index of the branch and the value.
+pub(crate) fn serialize_union<S>(u: &Union, object: &ObjectRef, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    if u.optional && object.is_none() {
+        return s.serialize_u32(0);
+    }
+    let index = variant_index(u, object).ser()?;
+    let mut tuple = s.serialize_tuple(2)?;
+    tuple.serialize_element(&((index + u.optional as usize) as u32))?;
+    tuple.serialize_element(&object.with_schema(&u.variants[index]))?;
+    tuple.end()
+}
+
+pub(crate) struct UnionSeed<'a>(pub &'a Union);
+
+impl<'a, 'de> DeserializeSeed<'de> for UnionSeed<'a> {
+    type Value = Object;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(2, self)
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for UnionSeed<'a> {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("a union"))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let index: u32 = match seq.next_element()? {
+            Some(i) => i,
+            None => return Err(error::invalid_length(0, &self)),
+        };
+        // `null` has no value.
+        let variant = match (self.0.optional, index) {
+            (true, 0) => return Ok(Object::new_none()),
+            (true, i) => self.0.variants.get(i as usize - 1),
+            (false, i) => self.0.variants.get(i as usize),
+        };
+        let schema = match variant {
+            Some(s) => s,
+            None => {
+                return Err(
+                    err!("unknown branch index `{}`", index).with_kind(ErrorKind::UnknownVariant)
+                )
+                .de()
+            }
+        };
+        match seq.next_element_seed(schema)? {
+            Some(v) => Ok(v),
+            None => Err(error::invalid_length(1, &self)),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+//! Represents datetimes, decimals and UUIDs with the native BSON types.
+//!
+//! The BSON format enables the native types with [`with_native_types`] while it
+//! runs the encoder and the decoder. Otherwise, these types are represented as
+//! strings as in the other formats.
+
+use crate::{
+    attr::AttrStr,
+    error::{self, Convert},
+    import::import,
+    object::{Object, ObjectRef},
+    schema::Schema,
+};
+use ::bson::{spec::BinarySubtype, Binary, Bson, DateTime, Decimal128};
+use serde::{
+    de::{Deserializer, Unexpected},
+    ser::{self, Serializer},
+    Deserialize, Serialize,
+};
+use std::cell::Cell;
+
+lazy_static::lazy_static! {
+    static ref ATTR_TZINFO: AttrStr = AttrStr::new("tzinfo");
+    static ref ATTR_BYTES: AttrStr = AttrStr::new("bytes");
+    static ref ATTR_UTC: AttrStr = AttrStr::new("utc");
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = Cell::new(false);
+}
+
+/// Runs `f` with the native types enabled.
+pub fn with_native_types<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let enabled = replace(true);
+    let res = f();
+    replace(enabled);
+    res
+}
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+/// Sets the mode and returns the previous one.
+pub(crate) fn replace(enabled: bool) -> bool {
+    ENABLED.with(|e| e.replace(enabled))
+}
+
+/// Serializes a datetime, decimal or UUID.
+pub(crate) fn serialize<S>(schema: &Schema, object: &ObjectRef, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match schema {
+        Schema::DateTime => to_datetime(object).ser()?.serialize(s),
+        Schema::Decimal => to_decimal(object).ser()?.serialize(s),
+        Schema::Uuid => {
+            let bytes = object.get_attr(&ATTR_BYTES).ser()?;
+            Binary {
+                subtype: BinarySubtype::Uuid,
+                bytes: bytes.as_bytes().ser()?.to_vec(),
+            }
+            .serialize(s)
+        }
+        _ => Err(ser::Error::custom(format_args!(
+            "`{}` has no native type",
+            schema.name()
+        ))),
+    }
+}
+
+fn to_datetime(object: &ObjectRef) -> error::Result<DateTime> {
+    let text = object.isoformat()?;
+    let text = text.as_str()?;
+
+    // BSON datetimes are in UTC, so naive datetimes are assumed to be in UTC.
+    let text = if object.get_attr(&ATTR_TZINFO)?.is_none() {
+        format!("{}Z", text)
+    } else {
+        text.into()
+    };
+    DateTime::parse_rfc3339_str(&text).map_err(|e| err!("{}", e))
+}
+
+fn to_decimal(object: &ObjectRef) -> error::Result<Decimal128> {
+    let text = object.to_str()?;
+    let text = text.as_str()?;
+    text.parse()
+        .map_err(|e| err!("cannot convert `{}` to decimal128: {}", text, e))
+}
+
+/// Converts a BSON datetime to an aware datetime in UTC.
+fn from_datetime(dt: DateTime) -> error::Result<Object> {
+    let t = dt.to_time_0_3();
+    let import = import()?;
+    let utc = import.timezone.get_attr(&ATTR_UTC)?;
+    import.datetime.call_with_kwargs(
+        vec![
+            Object::new_i64(t.year() as i64)?,
+            Object::new_i64(t.month() as i64)?,
+            Object::new_i64(t.day() as i64)?,
+            Object::new_i64(t.hour() as i64)?,
+            Object::new_i64(t.minute() as i64)?,
+            Object::new_i64(t.second() as i64)?,
+            Object::new_i64(t.microsecond() as i64)?,
+        ],
+        vec![(&ATTR_TZINFO, utc)],
+    )
+}
+
+fn from_uuid(bytes: &[u8]) -> error::Result<Object> {
+    import()?
+        .uuid
+        .call_with_kwargs(vec![], vec![(&ATTR_BYTES, Object::new_bytes(bytes)?)])
+}
+
+fn to_object(value: Bson) -> error::Result<Object> {
+    match value {
+        Bson::Double(v) => Object::new_f64(v),
+        Bson::String(v) => Object::new_str(&v),
+        Bson::Array(v) => {
+            let mut list = Object::build_list(v.len())?;
+            for (i, item) in v.into_iter().enumerate() {
+                list.set(i, to_object(item)?);
+            }
+            Ok(list.build())
+        }
+        Bson::Document(v) => {
+            let mut dict = Object::build_dict()?;
+            for (key, value) in v {
+                dict.set(Object::new_str(&key)?, to_object(value)?)?;
+            }
+            Ok(dict.build())
+        }
+        Bson::Boolean(v) => Ok(Object::new_bool(v)),
+        Bson::Null => Ok(Object::new_none()),
+        Bson::Int32(v) => Object::new_i64(v as i64),
+        Bson::Int64(v) => Object::new_i64(v),
+        Bson::DateTime(v) => from_datetime(v),
+        Bson::Decimal128(v) => Object::new_str(&v.to_string())?.into_decimal(),
+        Bson::Binary(v) if v.subtype == BinarySubtype::Uuid => from_uuid(&v.bytes),
+        Bson::Binary(v) => Object::new_bytes(&v.bytes),
+        Bson::ObjectId(v) => Object::new_str(&v.to_hex()),
+        v => bail!("unsupported BSON type `{:?}`", v.element_type()),
+    }
+}
+
+/// Returns the schema which the native type of the value is decoded as, if any.
+pub(crate) fn native_schema(value: &Bson) -> Option<Schema> {
+    match value {
+        Bson::DateTime(_) => Some(Schema::DateTime),
+        Bson::Decimal128(_) => Some(Schema::Decimal),
+        Bson::Binary(v) if v.subtype == BinarySubtype::Uuid => Some(Schema::Uuid),
+        Bson::ObjectId(_) => Some(Schema::Str),
+        _ => None,
+    }
+}
+
+fn unexpected(value: &Bson) -> Unexpected<'_> {
+    match value {
+        Bson::Double(v) => Unexpected::Float(*v),
+        Bson::String(v) => Unexpected::Str(v),
+        Bson::Array(_) => Unexpected::Seq,
+        Bson::Document(_) => Unexpected::Map,
+        Bson::Boolean(v) => Unexpected::Bool(*v),
+        Bson::Null => Unexpected::Unit,
+        Bson::Int32(v) => Unexpected::Signed(*v as i64),
+        Bson::Int64(v) => Unexpected::Signed(*v),
+        Bson::Binary(v) => Unexpected::Bytes(&v.bytes),
+        Bson::DateTime(_) => Unexpected::Other("datetime"),
+        Bson::Decimal128(_) => Unexpected::Other("decimal128"),
+        Bson::ObjectId(_) => Unexpected::Other("object id"),
+        _ => Unexpected::Other("BSON value"),
+    }
+}
+
+/// Decodes a string, datetime, decimal or UUID from either the native type or the string.
+///
+/// Object IDs are decoded as strings of hex digits.
+pub(crate) fn deserialize<'de, D>(schema: &Schema, deserializer: D) -> Result<Object, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Bson::deserialize(deserializer)?;
+    let obj = match (schema, value) {
+        (Schema::Str, Bson::String(v)) => Object::new_str(&v),
+        (Schema::Str, Bson::ObjectId(v)) => Object::new_str(&v.to_hex()),
+        (Schema::DateTime, Bson::DateTime(v)) => from_datetime(v),
+        (Schema::DateTime, Bson::String(v)) => Object::new_str(&v).and_then(|o| o.into_datetime()),
+        (Schema::Decimal, Bson::Decimal128(v)) => {
+            Object::new_str(&v.to_string()).and_then(|o| o.into_decimal())
+        }
+        (Schema::Decimal, Bson::String(v)) => Object::new_str(&v).and_then(|o| o.into_decimal()),
+        (Schema::Uuid, Bson::Binary(v)) if v.subtype == BinarySubtype::Uuid => from_uuid(&v.bytes),
+        (Schema::Uuid, Bson::String(v)) => Object::new_str(&v).and_then(|o| o.into_uuid()),
+        (schema, v) => return Err(error::invalid_type(unexpected(&v), &schema.name())),
+    };
+    obj.de()
+}
+
+/// Decodes any value including the native types.
+pub(crate) fn deserialize_any<'de, D>(deserializer: D) -> Result<Object, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    to_object(Bson::deserialize(deserializer)?).de()
+}
@@ -0,0 +1,476 @@
+//! Represents datetimes, decimals and UUIDs with the CBOR tags.
+//!
+//! The CBOR format enables the tags with [`with_tags`] while it runs the encoder
+//! and the decoder. Otherwise, these types are represented as strings as in the
+//! other formats.
+
+use crate::{
+    attr::AttrStr,
+    error::{self, Convert},
+    import::import,
+    object::{Object, ObjectRef},
+    schema::Schema,
+};
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, SeqAccess, Unexpected, Visitor},
+    forward_to_deserialize_any,
+    ser::{self, Serializer},
+    Deserialize, Serialize,
+};
+use serde_cbor::tags::{current_cbor_tag, Tagged};
+use std::{cell::Cell, convert::TryFrom, fmt};
+
+const TAG_DATETIME: u64 = 0;
+const TAG_EPOCH: u64 = 1;
+const TAG_BIGNUM: u64 = 2;
+const TAG_NEG_BIGNUM: u64 = 3;
+const TAG_DECIMAL: u64 = 4;
+const TAG_UUID: u64 = 37;
+
+lazy_static::lazy_static! {
+    static ref ATTR_TZINFO: AttrStr = AttrStr::new("tzinfo");
+    static ref ATTR_BYTES: AttrStr = AttrStr::new("bytes");
+    static ref ATTR_UTC: AttrStr = AttrStr::new("utc");
+    static ref ATTR_FROMTIMESTAMP: AttrStr = AttrStr::new("fromtimestamp");
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = Cell::new(false);
+    // The tag of the value replayed from the buffered input.
+    static REPLAYED_TAG: Cell<Option<u64>> = Cell::new(None);
+}
+
+/// Runs `f` with the tags enabled.
+pub fn with_tags<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let enabled = replace(true);
+    let res = f();
+    replace(enabled);
+    res
+}
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+/// Sets the mode and returns the previous one.
+pub(crate) fn replace(enabled: bool) -> bool {
+    ENABLED.with(|e| e.replace(enabled))
+}
+
+struct Bytes<'a>(&'a [u8]);
+
+impl<'a> Serialize for Bytes<'a> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_bytes(self.0)
+    }
+}
+
+/// The mantissa of a decimal fraction, which becomes a bignum if it doesn't fit in `i64`.
+struct Mantissa(i128);
+
+impl Serialize for Mantissa {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if let Ok(v) = i64::try_from(self.0) {
+            return s.serialize_i64(v);
+        }
+        let (tag, v) = if self.0 < 0 {
+            (TAG_NEG_BIGNUM, !self.0 as u128)
+        } else {
+            (TAG_BIGNUM, self.0 as u128)
+        };
+        let bytes = v.to_be_bytes();
+        let zeros = bytes.iter().take_while(|b| **b == 0).count();
+        Tagged::new(Some(tag), Bytes(&bytes[zeros..])).serialize(s)
+    }
+}
+
+/// Serializes a datetime, decimal or UUID.
+pub(crate) fn serialize<S>(schema: &Schema, object: &ObjectRef, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match schema {
+        Schema::DateTime => serialize_datetime(object, s),
+        Schema::Decimal => serialize_decimal(object, s),
+        Schema::Uuid => serialize_uuid(object, s),
+        _ => Err(ser::Error::custom(format_args!(
+            "`{}` has no tag",
+            schema.name()
+        ))),
+    }
+}
+
+fn serialize_datetime<S>(object: &ObjectRef, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let text = object.isoformat().ser()?;
+    let text = text.as_str().ser()?;
+
+    // RFC 3339 requires the offset, so naive datetimes stay plain strings.
+    if object.get_attr(&ATTR_TZINFO).ser()?.is_none() {
+        s.serialize_str(text)
+    } else {
+        Tagged::new(Some(TAG_DATETIME), text).serialize(s)
+    }
+}
+
+fn serialize_decimal<S>(object: &ObjectRef, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let text = object.to_str().ser()?;
+    let text = text.as_str().ser()?;
+
+    match decimal_fraction(text) {
+        // The mantissa can't keep the sign of negative zero.
+        Some((_, 0)) if text.starts_with('-') => s.serialize_str(text),
+        Some((exponent, mantissa)) => {
+            Tagged::new(Some(TAG_DECIMAL), (exponent, Mantissa(mantissa))).serialize(s)
+        }
+        // Infinity, NaN or too many digits.
+        None => s.serialize_str(text),
+    }
+}
+
+fn serialize_uuid<S>(object: &ObjectRef, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let bytes = object.get_attr(&ATTR_BYTES).ser()?;
+    Tagged::new(Some(TAG_UUID), Bytes(bytes.as_bytes().ser()?)).serialize(s)
+}
+
+/// Splits the string representation of a decimal into the exponent and the mantissa.
+pub(crate) fn decimal_fraction(s: &str) -> Option<(i64, i128)> {
+    let (digits, exponent) = match s.find(|c| c == 'E' || c == 'e') {
+        Some(i) => (&s[..i], s[i + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int, frac) = match digits.find('.') {
+        Some(i) => (&digits[..i], &digits[i + 1..]),
+        None => (digits, ""),
+    };
+    let mantissa = format!("{}{}", int, frac).parse().ok()?;
+    Some((exponent - frac.len() as i64, mantissa))
+}
+
+fn datetime_from_str(s: &str) -> error::Result<Object> {
+    // `fromisoformat` doesn't accept `Z` before Python 3.11.
+    let s = if s.ends_with(|c| c == 'Z' || c == 'z') {
+        format!("{}+00:00", &s[..s.len() - 1])
+    } else {
+        s.into()
+    };
+    Object::new_str(&s)?.into_datetime()
+}
+
+fn datetime_from_epoch(epoch: Object) -> error::Result<Object> {
+    let import = import()?;
+    let utc = import.timezone.get_attr(&ATTR_UTC)?;
+    import
+        .datetime
+        .get_attr(&ATTR_FROMTIMESTAMP)?
+        .call(vec![epoch, utc])
+}
+
+fn uuid_from_bytes(bytes: &[u8]) -> error::Result<Object> {
+    import()?
+        .uuid
+        .call_with_kwargs(vec![], vec![(&ATTR_BYTES, Object::new_bytes(bytes)?)])
+}
+
+pub(crate) fn decimal_from_fraction(exponent: i64, mantissa: i128) -> error::Result<Object> {
+    Object::new_str(&format!("{}E{}", mantissa, exponent))?.into_decimal()
+}
+
+/// Returns true if the tag is converted by [`Tag`].
+pub(crate) fn is_known(tag: u64) -> bool {
+    matches!(tag, TAG_DATETIME | TAG_EPOCH | TAG_DECIMAL | TAG_UUID)
+}
+
+/// Returns true if the value with the tag can be decoded as the schema.
+pub(crate) fn accepts(schema: &Schema, tag: u64) -> bool {
+    matches!(
+        (schema, tag),
+        (Schema::DateTime, TAG_DATETIME)
+            | (Schema::DateTime, TAG_EPOCH)
+            | (Schema::Decimal, TAG_DECIMAL)
+            | (Schema::Uuid, TAG_UUID)
+    )
+}
+
+/// Returns the tag of the value being decoded.
+pub(crate) fn current_tag() -> Option<u64> {
+    REPLAYED_TAG.with(|t| t.get()).or_else(current_cbor_tag)
+}
+
+/// Runs `f` with the tag of the value replayed from the buffered input,
+/// which is kept by [`Content`](crate::decode::content::Content).
+pub(crate) fn with_replayed_tag<T, F>(tag: u64, f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let prev = REPLAYED_TAG.with(|t| t.replace(Some(tag)));
+    let res = f();
+    REPLAYED_TAG.with(|t| t.set(prev));
+    res
+}
+
+/// Decodes the content of a tagged value.
+pub(crate) struct Tag(pub u64);
+
+impl<'de> DeserializeSeed<'de> for Tag {
+    type Value = Object;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match self.0 {
+            TAG_DATETIME => {
+                let s = String::deserialize(deserializer)?;
+                datetime_from_str(&s).de()
+            }
+            TAG_EPOCH => deserializer.deserialize_any(EpochVisitor),
+            TAG_DECIMAL => deserializer.deserialize_seq(DecimalVisitor),
+            TAG_UUID => deserializer.deserialize_bytes(UuidVisitor),
+            _ => Err(de::Error::custom(format_args!("unknown tag `{}`", self.0))),
+        }
+    }
+}
+
+/// Passes the content of the tagged value to the visitor as the tagged value again,
+/// e.g. after the union has picked the variant by the tag.
+pub(crate) struct TaggedDeserializer<D>(pub D);
+
+impl<'de, D> Deserializer<'de> for TaggedDeserializer<D>
+where
+    D: Deserializer<'de>,
+{
+    type Error = D::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self.0)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Decodes a datetime, decimal or UUID from either the tagged value or the string.
+pub(crate) struct TaggedVisitor<'a>(pub &'a Schema);
+
+impl<'a, 'de> Visitor<'de> for TaggedVisitor<'a> {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("{}", self.0.name()))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let s = Object::new_str(v).de()?;
+        match self.0 {
+            Schema::DateTime => s.into_datetime().de(),
+            Schema::Decimal => s.into_decimal().de(),
+            Schema::Uuid => s.into_uuid().de(),
+            _ => Err(error::invalid_type(Unexpected::Str(v), &self)),
+        }
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match self.0 {
+            Schema::Uuid => uuid_from_bytes(v).de(),
+            _ => Err(error::invalid_type(Unexpected::Bytes(v), &self)),
+        }
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match current_tag() {
+            Some(tag) if accepts(self.0, tag) => Tag(tag).deserialize(deserializer),
+            // Unknown tags are ignored.
+            _ => deserializer.deserialize_any(self),
+        }
+    }
+}
+
+struct EpochVisitor;
+
+impl<'de> Visitor<'de> for EpochVisitor {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("epoch-based date/time"))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        datetime_from_epoch(Object::new_i64(v).de()?).de()
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        datetime_from_epoch(Object::new_u64(v).de()?).de()
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        datetime_from_epoch(Object::new_f64(v).de()?).de()
+    }
+}
+
+struct DecimalVisitor;
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("decimal fraction"))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let exponent: i64 = seq
+            .next_element()?
+            .ok_or_else(|| error::invalid_length(0, &self))?;
+        let mantissa = seq
+            .next_element_seed(MantissaVisitor)?
+            .ok_or_else(|| error::invalid_length(1, &self))?;
+        if seq.next_element::<de::IgnoredAny>()?.is_some() {
+            return Err(error::invalid_length(3, &self));
+        }
+        decimal_from_fraction(exponent, mantissa).de()
+    }
+}
+
+struct MantissaVisitor;
+
+impl<'de> DeserializeSeed<'de> for MantissaVisitor {
+    type Value = i128;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for MantissaVisitor {
+    type Value = i128;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("an integer or a bignum"))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v as i128)
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v as i128)
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(v)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let tag = current_tag();
+        let bytes = deserializer.deserialize_bytes(BignumVisitor)?;
+        match tag {
+            Some(TAG_BIGNUM) => {
+                i128::try_from(bytes).map_err(|_| de::Error::custom("bignum too large"))
+            }
+            Some(TAG_NEG_BIGNUM) => i128::try_from(bytes)
+                .map(|v| !v)
+                .map_err(|_| de::Error::custom("bignum too large")),
+            _ => Err(error::invalid_type(Unexpected::NewtypeStruct, &self)),
+        }
+    }
+}
+
+struct BignumVisitor;
+
+impl<'de> Visitor<'de> for BignumVisitor {
+    type Value = u128;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("bignum"))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let v = match v.iter().position(|b| *b != 0) {
+            Some(i) => &v[i..],
+            None => &[],
+        };
+        if v.len() > 16 {
+            return Err(de::Error::custom("bignum too large"));
+        }
+        Ok(v.iter().fold(0, |n, b| (n << 8) | *b as u128))
+    }
+}
+
+struct UuidVisitor;
+
+impl<'de> Visitor<'de> for UuidVisitor {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("UUID bytes"))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        uuid_from_bytes(v).de()
+    }
+}
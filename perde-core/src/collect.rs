@@ -0,0 +1,155 @@
+//! Collects the errors instead of stopping at the first one.
+//!
+//! The visitors call [`recover`] on the result of each element. When the
+//! collection is enabled, the error is recorded with its location relative
+//! to the visitor and the visitor moves on to the next element. Once done,
+//! the visitor returns [`failed`] if anything below it failed, so that the
+//! parent can prefix the location of the recorded errors.
+
+use crate::{
+    decode::content::{self, Content},
+    error::{self, Error},
+    object::Object,
+    path, positional,
+    schema::Schema,
+};
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer};
+use std::{cell::RefCell, fmt::Display};
+
+#[derive(Default)]
+pub(crate) struct Collector {
+    enabled: bool,
+    errors: Vec<Error>,
+    // True while the error returned by `failed` is unwinding.
+    failed: bool,
+}
+
+thread_local! {
+    static COLLECTOR: RefCell<Collector> = RefCell::new(Collector::default());
+}
+
+/// Replaces the state of the collection, e.g. to run a nested entry point.
+pub(crate) fn replace(collector: Collector) -> Collector {
+    COLLECTOR.with(|c| c.replace(collector))
+}
+
+pub(crate) fn begin(enabled: bool) {
+    COLLECTOR.with(|c| {
+        let mut c = c.borrow_mut();
+        c.enabled = enabled;
+        c.errors.clear();
+        c.failed = false;
+    })
+}
+
+pub(crate) fn end() -> Vec<Error> {
+    COLLECTOR.with(|c| {
+        let mut c = c.borrow_mut();
+        c.enabled = false;
+        c.failed = false;
+        std::mem::take(&mut c.errors)
+    })
+}
+
+pub fn enabled() -> bool {
+    COLLECTOR.with(|c| c.borrow().enabled)
+}
+
+/// Runs `f` with the collection disabled, e.g. to try a variant of a union
+/// whose errors must not be reported.
+pub(crate) fn suspend<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let enabled = COLLECTOR.with(|c| std::mem::replace(&mut c.borrow_mut().enabled, false));
+    let res = f();
+    COLLECTOR.with(|c| c.borrow_mut().enabled = enabled);
+    res
+}
+
+/// Returns the position to be passed to [`failed_since`].
+pub(crate) fn mark() -> usize {
+    COLLECTOR.with(|c| c.borrow().errors.len())
+}
+
+/// Returns true if any error has been recorded since the mark.
+pub(crate) fn failed_since(mark: usize) -> bool {
+    COLLECTOR.with(|c| c.borrow().errors.len() > mark)
+}
+
+/// Returns true if any error has been recorded.
+///
+/// The output is discarded in that case, so the visitors don't need
+/// to construct the objects any more.
+pub(crate) fn has_errors() -> bool {
+    failed_since(0)
+}
+
+/// Runs the deserialization of an element, and records the error and
+/// returns `None` if the collection is enabled.
+pub(crate) fn recover<T, E, F>(f: F) -> Result<Option<T>, E>
+where
+    E: Display,
+    F: FnOnce() -> Result<T, E>,
+{
+    let mark = mark();
+    let e = match f() {
+        Ok(v) => return Ok(Some(v)),
+        Err(e) => e,
+    };
+
+    if !enabled() {
+        return Err(e);
+    }
+
+    let relative = path::take();
+    let kind = error::take_kind();
+    COLLECTOR.with(|c| {
+        let mut c = c.borrow_mut();
+        if std::mem::take(&mut c.failed) {
+            // The errors are already recorded by the element.
+            for error in &mut c.errors[mark..] {
+                let path = relative.join(error.path());
+                error.set_path(path);
+            }
+        } else {
+            c.errors
+                .push(Error::new(e).with_kind(kind).with_path(relative));
+        }
+    });
+    Ok(None)
+}
+
+/// Records the error found by the visitor itself, e.g. a missing field.
+pub(crate) fn push(e: Error) {
+    COLLECTOR.with(|c| c.borrow_mut().errors.push(e))
+}
+
+/// Returns the error telling the parent that the errors are recorded.
+pub(crate) fn failed() -> Error {
+    COLLECTOR.with(|c| c.borrow_mut().failed = true);
+    Error::new("invalid input")
+}
+
+/// Buffers the whole input first, so that the visitors can recover from
+/// the errors regardless of the input format.
+pub struct Collect<'a>(pub &'a Schema);
+
+impl<'a, 'de> DeserializeSeed<'de> for Collect<'a> {
+    type Value = Object;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // The input can't be buffered without the types, and the position in
+        // the input is lost once an element fails.
+        if positional::enabled() {
+            return Err(de::Error::custom(
+                "errors cannot be collected from formats which are not self-describing",
+            ));
+        }
+        let content = Content::deserialize(deserializer)?;
+        content::from_content(&content, self.0).map_err(de::Error::custom)
+    }
+}
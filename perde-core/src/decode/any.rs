@@ -1,4 +1,4 @@
-use crate::{error::Convert, object::Object, schema::Any};
+use crate::{bson, cbor, error::Convert, object::Object, schema::Any};
 use serde::{
     de::{DeserializeSeed, Deserializer, EnumAccess, Error, MapAccess, SeqAccess, Visitor},
     Deserialize,
@@ -165,7 +165,10 @@ impl<'de> Visitor<'de> for AnyVisitor {
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_any(AnyVisitor)
+        match cbor::current_tag() {
+            Some(tag) if cbor::is_known(tag) => cbor::Tag(tag).deserialize(deserializer),
+            _ => deserializer.deserialize_any(AnyVisitor),
+        }
     }
 
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
@@ -216,6 +219,9 @@ impl<'de> Deserialize<'de> for Object {
     where
         D: Deserializer<'de>,
     {
+        if bson::enabled() {
+            return bson::deserialize_any(de);
+        }
         de.deserialize_any(AnyVisitor)
     }
 }
@@ -227,6 +233,6 @@ impl<'a, 'de> DeserializeSeed<'de> for &'a Any {
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_any(AnyVisitor)
+        Object::deserialize(deserializer)
     }
 }
@@ -1,10 +1,12 @@
 use crate::{
-    error::{Convert, Result},
+    collect,
+    error::{self, Convert, ErrorKind, Result},
     object::Object,
+    path::{Locate, Segment},
     schema::*,
 };
 use indexmap::IndexMap;
-use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
 use std::{borrow::Cow, fmt};
 
 pub struct ClassVisitor<'a>(pub &'a Class);
@@ -13,36 +15,155 @@ impl<'a, 'de> Visitor<'de> for ClassVisitor<'a> {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a class")
+        error::expecting(f, format_args!("a class"))
     }
 
+    untag!();
+
     fn visit_map<M>(self, mut access: M) -> std::result::Result<Self::Value, M::Error>
     where
         M: MapAccess<'de>,
     {
         let mut map = IndexMap::new();
+        let mark = collect::mark();
 
         while let Some(key) = access.next_key()? {
             let key: Cow<str> = key;
 
-            if let Some(s) = self.0.field(&key).de()? {
-                let value: Object = access.next_value_seed(&s.schema)?;
-
-                map.insert(key, value);
-            } else if let Some(flatten_dict) = self.0.flatten_dict.as_ref() {
-                let value: Object = access.next_value_seed(&*flatten_dict.value)?;
-                map.insert(key, value);
-            } else {
+            // The tag is written by the class itself, and checked by the union if any.
+            if self.0.internal_tag() == Some(&key) {
                 let _: IgnoredAny = access.next_value()?;
+                continue;
+            }
+
+            let field = collect::recover(|| {
+                self.0
+                    .field(&key)
+                    .at(|| Segment::Field(key.to_string()))
+                    .de()
+            })?;
+
+            // The value of unknown fields goes to the flattened dict if any.
+            let value = match (field, &self.0.flatten_dict) {
+                (Some(Some(s)), _) => collect::recover(|| {
+                    access
+                        .next_value_seed(FieldSeed(s))
+                        .at(|| Segment::Field(key.to_string()))
+                })?,
+                (Some(None), Some(d)) => collect::recover(|| {
+                    access
+                        .next_value_seed(&*d.value)
+                        .at(|| Segment::Field(key.to_string()))
+                })?,
+                _ => {
+                    let _: IgnoredAny = access.next_value()?;
+                    continue;
+                }
+            };
+            // The values given by the aliases are stored with the field names.
+            let key = match self.0.aliases.get(key.as_ref()) {
+                Some(name) => Cow::Borrowed(name.as_str()),
+                None => key,
+            };
+            // The field is given by both the name and the alias, or more than once.
+            if map.contains_key(&key) {
+                return Err(err!("duplicate field `{}`", key))
+                    .at(|| Segment::Field(key.to_string()))
+                    .de();
+            }
+            // Put the placeholder not to report the field as missing.
+            map.insert(key, value.unwrap_or_else(Object::new_none));
+        }
+
+        let cls = self.0.call(&mut map).de()?;
+
+        if collect::failed_since(mark) {
+            return Err(collect::failed()).de();
+        }
+
+        Ok(cls)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut map = IndexMap::new();
+        let mark = collect::mark();
+
+        // The elements are assigned to the fields in order.
+        // The missing trailing fields are handled in the same way as the missing fields of maps.
+        let fields = self
+            .0
+            .fields
+            .iter()
+            .filter(|(_, s)| !s.attr.skip && !s.attr.skip_deserializing);
+        let mut len = 0;
+        let mut end = false;
+        for (key, s) in fields {
+            let value = collect::recover(|| {
+                seq.next_element_seed(FieldSeed(s))
+                    .at(|| Segment::Field(key.to_string()))
+            })?;
+            let value = match value {
+                Some(Some(v)) => v,
+                Some(None) => {
+                    end = true;
+                    break;
+                }
+                None => Object::new_none(),
+            };
+            map.insert(Cow::Borrowed(key.as_str()), value);
+            len += 1;
+        }
+
+        // The extra elements are ignored like unknown fields.
+        if !end && seq.next_element::<IgnoredAny>()?.is_some() {
+            if self.0.attr.deny_unknown_fields {
+                return Err(err!("too many elements, expected at most {}", len)
+                    .with_kind(ErrorKind::InvalidLength))
+                .de();
             }
+            while seq.next_element::<IgnoredAny>()?.is_some() {}
         }
 
         let cls = self.0.call(&mut map).de()?;
 
+        if collect::failed_since(mark) {
+            return Err(collect::failed()).de();
+        }
+
         Ok(cls)
     }
 }
 
+/// Deserializes the value of a field, applying `perde_deserialize_with` or the constraints.
+pub(crate) struct FieldSeed<'a>(pub(crate) &'a FieldSchema);
+
+impl<'a, 'de> DeserializeSeed<'de> for FieldSeed<'a> {
+    type Value = Object;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let attr = &self.0.attr;
+
+        let v = match &attr.deserialize_with {
+            Some(f) => {
+                // The hook receives the value as it is in the input.
+                let v = static_schema().any.deserialize(deserializer)?;
+                f.call1(v)
+                    .map_err(|e| e.describe_exception())
+                    .context("error in `perde_deserialize_with`")
+                    .de()?
+            }
+            None => self.0.schema.deserialize(deserializer)?,
+        };
+        attr.constraints.validate(&v).de().map(|_| v)
+    }
+}
+
 impl<'a, 'de> DeserializeSeed<'de> for &'a Class {
     type Value = Object;
 
@@ -50,7 +171,17 @@ impl<'a, 'de> DeserializeSeed<'de> for &'a Class {
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_map(ClassVisitor(self))
+        if let Some((key, field)) = self.transparent_field() {
+            // The input is the value of the only field.
+            let value = FieldSeed(field).deserialize(deserializer)?;
+            let mut map = IndexMap::new();
+            map.insert(Cow::Borrowed(key.as_str()), value);
+            self.call(&mut map).de()
+        } else if self.attr.as_array {
+            deserializer.deserialize_seq(ClassVisitor(self))
+        } else {
+            deserializer.deserialize_map(ClassVisitor(self))
+        }
     }
 }
 
@@ -70,6 +201,8 @@ impl Class {
             &self.flatten_fields
         };
 
+        let name = self.aliases.get(name).map_or(name, |n| n.as_str());
+
         map.get(name)
             .map(|v| {
                 if v.attr.skip || v.attr.skip_deserializing {
@@ -80,7 +213,7 @@ impl Class {
             })
             .unwrap_or_else(|| {
                 if self.attr.deny_unknown_fields {
-                    Err(err!("unknown field `{}`", name,))
+                    Err(err!("unknown field `{}`", name,).with_kind(ErrorKind::UnknownField))
                 } else {
                     Ok(None)
                 }
@@ -88,9 +221,19 @@ impl Class {
     }
 
     pub fn call(&self, map: &mut IndexMap<Cow<str>, Object>) -> Result<Object> {
+        // The fields with `init=False` are not the arguments of the constructor.
+        // They are set after the construction if they are in the input.
+        let uninit: Vec<_> = self
+            .fields
+            .iter()
+            .filter(|(_, s)| !s.attr.init)
+            .filter_map(|(k, s)| map.shift_remove(k.as_str()).map(|v| (s, v)))
+            .collect();
+
         let args: Result<Vec<_>> = self
             .fields
             .iter()
+            .filter(|(_, s)| s.attr.init)
             .map(|(k, s)| {
                 if s.attr.flatten {
                     match &s.schema {
@@ -139,12 +282,39 @@ impl Class {
                             return Object::new_default(&s.schema);
                         }
 
-                        Err(err!("missing field \"{}\"", k))
+                        let e = err!("missing field \"{}\"", k).with_kind(ErrorKind::MissingField);
+                        if collect::enabled() {
+                            collect::push(e.with_path(Segment::Field(k.to_string()).into()));
+                            return Ok(Object::new_none());
+                        }
+
+                        Err(e).at(|| Segment::Field(k.to_string()))
                     }
                 }
             })
             .collect();
+        let args = args?;
+
+        if collect::has_errors() {
+            // The output is discarded, so don't construct the object.
+            return Ok(Object::new_none());
+        }
 
-        self.ty.call(args?)
+        // The keyword-only fields are moved after the positional ones
+        // in the signature of the constructor, so pass them by name.
+        let (kwargs, args): (Vec<_>, Vec<_>) = self
+            .fields
+            .values()
+            .filter(|s| s.attr.init)
+            .zip(args)
+            .partition(|(s, _)| s.attr.kw_only);
+        let args = args.into_iter().map(|(_, v)| v).collect();
+        let kwargs = kwargs.into_iter().map(|(s, v)| (&s.arg_name, v)).collect();
+
+        let obj = self.ty.call_with_kwargs(args, kwargs)?;
+        for (s, v) in uninit {
+            obj.set_attr_generic(&s.name, v)?;
+        }
+        Ok(obj)
     }
 }
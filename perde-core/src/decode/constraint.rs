@@ -0,0 +1,62 @@
+use crate::{
+    error::{ErrorKind, Result},
+    object::ObjectRef,
+    schema::*,
+};
+
+macro_rules! invalid {
+    ($($t:tt)*) => {
+        return Err(err!($($t)*).with_kind(ErrorKind::Validation))
+    };
+}
+
+impl Constraints {
+    /// Checks the decoded value of the field.
+    pub fn validate(&self, value: &ObjectRef) -> Result<()> {
+        // `None` of optional fields isn't constrained.
+        if self.is_empty() || value.is_none() {
+            return Ok(());
+        }
+
+        if let Some(ge) = &self.ge {
+            if !value.ge(ge)? {
+                invalid!(
+                    "the value must be greater than or equal to {:?}: got {:?}",
+                    ge.repr()?,
+                    value.repr()?
+                );
+            }
+        }
+        if let Some(le) = &self.le {
+            if !value.le(le)? {
+                invalid!(
+                    "the value must be less than or equal to {:?}: got {:?}",
+                    le.repr()?,
+                    value.repr()?
+                );
+            }
+        }
+
+        if self.min_len.is_some() || self.max_len.is_some() {
+            let len = value.length()?;
+            if let Some(min) = self.min_len.filter(|min| len < *min) {
+                invalid!("the length must be at least {}: got {}", min, len);
+            }
+            if let Some(max) = self.max_len.filter(|max| len > *max) {
+                invalid!("the length must be at most {}: got {}", max, len);
+            }
+        }
+
+        if let Some(pattern) = &self.pattern {
+            if pattern.search.call1(value.owned())?.is_none() {
+                invalid!(
+                    "the value must match the pattern `{}`: got {:?}",
+                    pattern.pattern,
+                    value.repr()?
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,390 @@
+use crate::{
+    cbor,
+    error::{self, ErrorKind},
+};
+use serde::{
+    de::{
+        self, value::SeqDeserializer, DeserializeSeed, Deserializer, EnumAccess, Expected,
+        MapAccess, SeqAccess, Unexpected, Visitor,
+    },
+    forward_to_deserialize_any, Deserialize,
+};
+use std::{
+    fmt::{self, Display},
+    marker::PhantomData,
+};
+
+/// The input buffered in memory, which can be deserialized repeatedly.
+///
+/// This follows `Content` private to serde, used for its untagged enums, and
+/// `Value` of `serde-value`. Enums are rejected as serde does, because the
+/// payload can't be read without knowing the kind of the variant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Content {
+    Bool(bool),
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Str(String),
+    Bytes(Vec<u8>),
+    None,
+    Some(Box<Content>),
+    Unit,
+    Newtype(Box<Content>),
+    /// The value with the CBOR tag.
+    Tagged(u64, Box<Content>),
+    Seq(Vec<Content>),
+    Map(Vec<(Content, Content)>),
+}
+
+struct ContentVisitor;
+
+impl<'de> Visitor<'de> for ContentVisitor {
+    type Value = Content;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "any value")
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::I64(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::U64(v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::F64(v))
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::Str(v.into()))
+    }
+
+    fn visit_string<E>(self, v: String) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::Str(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::Bytes(v.into()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::Bytes(v))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::None)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Content::deserialize(deserializer).map(|v| Content::Some(Box::new(v)))
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Content::Unit)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let tag = cbor::current_tag();
+        let v = Box::new(Content::deserialize(deserializer)?);
+        Ok(match tag {
+            Some(tag) => Content::Tagged(tag, v),
+            None => Content::Newtype(v),
+        })
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            items.push(item);
+        }
+        Ok(Content::Seq(items))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut items = Vec::with_capacity(map.size_hint().unwrap_or(0));
+        while let Some(item) = map.next_entry()? {
+            items.push(item);
+        }
+        Ok(Content::Map(items))
+    }
+
+    fn visit_enum<A>(self, _: A) -> Result<Self::Value, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        Err(error::invalid_type(Unexpected::Enum, &self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Content {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(ContentVisitor)
+    }
+}
+
+/// The error raised while deserializing the buffered input.
+/// The kind of the error is reported to the collector.
+#[derive(Debug)]
+pub struct ContentError(String);
+
+impl Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for ContentError {}
+
+impl ContentError {
+    fn with_kind<T: Display>(kind: ErrorKind, msg: T) -> Self {
+        error::set_kind(kind);
+        Self(msg.to_string())
+    }
+}
+
+impl de::Error for ContentError {
+    fn custom<T: Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+
+    fn invalid_type(unexp: Unexpected, exp: &dyn Expected) -> Self {
+        Self::with_kind(
+            ErrorKind::InvalidType,
+            format_args!("invalid type: {}, expected {}", unexp, exp),
+        )
+    }
+
+    fn invalid_value(unexp: Unexpected, exp: &dyn Expected) -> Self {
+        Self::with_kind(
+            ErrorKind::InvalidValue,
+            format_args!("invalid value: {}, expected {}", unexp, exp),
+        )
+    }
+
+    fn invalid_length(len: usize, exp: &dyn Expected) -> Self {
+        Self::with_kind(
+            ErrorKind::InvalidLength,
+            format_args!("invalid length {}, expected {}", len, exp),
+        )
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+        Self::with_kind(
+            ErrorKind::UnknownVariant,
+            format_args!("unknown variant `{}`, expected {:?}", variant, expected),
+        )
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        Self::with_kind(
+            ErrorKind::UnknownField,
+            format_args!("unknown field `{}`, expected {:?}", field, expected),
+        )
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self::with_kind(
+            ErrorKind::MissingField,
+            format_args!("missing field `{}`", field),
+        )
+    }
+}
+
+/// Deserializer reading the buffered input.
+pub struct ContentDeserializer<'a, E> {
+    content: &'a Content,
+    marker: PhantomData<E>,
+}
+
+impl<'a, E> ContentDeserializer<'a, E> {
+    pub fn new(content: &'a Content) -> Self {
+        Self {
+            content,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, E> Deserializer<'de> for ContentDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::Bool(v) => visitor.visit_bool(*v),
+            Content::I64(v) => visitor.visit_i64(*v),
+            Content::U64(v) => visitor.visit_u64(*v),
+            Content::F64(v) => visitor.visit_f64(*v),
+            Content::Str(v) => visitor.visit_borrowed_str(v),
+            Content::Bytes(v) => visitor.visit_borrowed_bytes(v),
+            Content::None => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(ContentDeserializer::new(v)),
+            Content::Unit => visitor.visit_unit(),
+            Content::Newtype(v) => visitor.visit_newtype_struct(ContentDeserializer::new(v)),
+            Content::Tagged(tag, v) => cbor::with_replayed_tag(*tag, || {
+                visitor.visit_newtype_struct(ContentDeserializer::new(v))
+            }),
+            Content::Seq(v) => {
+                let mut seq = SeqDeserializer::new(v.iter().map(ContentDeserializer::new));
+                let value = visitor.visit_seq(&mut seq)?;
+                seq.end()?;
+                Ok(value)
+            }
+            Content::Map(v) => visitor.visit_map(ContentMapAccess::<E>::new(v)),
+        }
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::None | Content::Unit => visitor.visit_none(),
+            Content::Some(v) => visitor.visit_some(ContentDeserializer::new(v)),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _: &'static str, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+    {
+        match self.content {
+            Content::Newtype(v) => visitor.visit_newtype_struct(ContentDeserializer::new(v)),
+            Content::Tagged(..) => self.deserialize_any(visitor),
+            _ => visitor.visit_newtype_struct(self),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+impl<'de, E> de::IntoDeserializer<'de, E> for ContentDeserializer<'de, E>
+where
+    E: de::Error,
+{
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+struct ContentMapAccess<'a, E> {
+    iter: std::slice::Iter<'a, (Content, Content)>,
+    value: Option<&'a Content>,
+    marker: PhantomData<E>,
+}
+
+impl<'a, E> ContentMapAccess<'a, E> {
+    fn new(items: &'a [(Content, Content)]) -> Self {
+        Self {
+            iter: items.iter(),
+            value: None,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'de, E> MapAccess<'de> for ContentMapAccess<'de, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, E>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.iter.next() {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(ContentDeserializer::new(k)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, E>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        match self.value.take() {
+            Some(v) => seed.deserialize(ContentDeserializer::new(v)),
+            None => Err(de::Error::custom("value is missing")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+/// Deserializes the value from the buffered input.
+pub fn from_content<'a, T>(content: &'a Content, seed: T) -> Result<T::Value, ContentError>
+where
+    T: DeserializeSeed<'a>,
+{
+    seed.deserialize(ContentDeserializer::new(content))
+}
@@ -1,5 +1,11 @@
-use crate::{error::Convert, object::Object, schema::*};
-use serde::de::{DeserializeSeed, Deserializer, MapAccess, Visitor};
+use crate::{
+    collect,
+    error::{self, Convert},
+    object::Object,
+    path::{Locate, Segment},
+    schema::*,
+};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
 use std::fmt;
 
 pub struct DictVisitor<'a>(pub &'a Dict);
@@ -8,19 +14,41 @@ impl<'a, 'de> Visitor<'de> for DictVisitor<'a> {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a map")
+        error::expecting(f, format_args!("a map"))
     }
 
+    untag!();
+
     fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
     where
         M: MapAccess<'de>,
     {
         let mut dict = Object::build_dict().de()?;
 
-        while let Some(key) = access.next_key_seed(&*self.0.key)? {
-            let key: Object = key;
-            let value: Object = access.next_value_seed(&*self.0.value)?;
-            dict.set(key, value).de()?;
+        let mark = collect::mark();
+
+        loop {
+            let key = collect::recover(|| access.next_key_seed(&*self.0.key))?;
+            let key: Object = match key {
+                Some(Some(key)) => key,
+                Some(None) => break,
+                None => {
+                    let _: IgnoredAny = access.next_value()?;
+                    continue;
+                }
+            };
+            let value = collect::recover(|| {
+                access
+                    .next_value_seed(&*self.0.value)
+                    .at(|| Segment::key(&key))
+            })?;
+            if let Some(value) = value {
+                dict.set(key, value).de()?;
+            }
+        }
+
+        if collect::failed_since(mark) {
+            return Err(collect::failed()).de();
         }
 
         Ok(dict.build())
@@ -1,4 +1,9 @@
-use crate::{decode::any::AnyVisitor, error::Convert, object::Object, schema::*};
+use crate::{
+    decode::any::AnyVisitor,
+    error::{self, Convert, ErrorKind},
+    object::Object,
+    schema::*,
+};
 use serde::de::{self, DeserializeSeed, Deserializer, Visitor};
 use std::fmt;
 
@@ -33,11 +38,13 @@ impl<'a> EnumVisitor<'a> {
             None => match self.0.variants.iter().find(|v| v.attr.other) {
                 Some(e) => e,
                 None => {
-                    return Err(de::Error::custom(format!(
+                    return Err(err!(
                         "the enum value must be any of {:?}: got `{}`",
                         self.vars(),
                         s
-                    )))
+                    )
+                    .with_kind(ErrorKind::UnknownVariant))
+                    .de()
                 }
             },
         };
@@ -54,9 +61,11 @@ impl<'a, 'de> Visitor<'de> for EnumVisitor<'a> {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "an enum value: {:?}", self.vars())
+        error::expecting(f, format_args!("an enum value: {:?}", self.vars()))
     }
 
+    untag!();
+
     fn visit_char<E>(self, value: char) -> Result<Self::Value, E>
     where
         E: de::Error,
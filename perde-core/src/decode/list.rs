@@ -1,4 +1,10 @@
-use crate::{error::Convert, object::Object, schema::*};
+use crate::{
+    collect,
+    error::{self, Convert},
+    object::Object,
+    path::{Locate, Segment},
+    schema::*,
+};
 use serde::de::{DeserializeSeed, Deserializer, SeqAccess, Visitor};
 use smallvec::SmallVec;
 use std::fmt;
@@ -9,17 +15,34 @@ impl<'a, 'de> Visitor<'de> for ListVisitor<'a> {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a list")
+        error::expecting(f, format_args!("a list"))
     }
 
+    untag!();
+
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
     where
         A: SeqAccess<'de>,
     {
         let mut items = SmallVec::<[_; 16]>::new();
+        let mark = collect::mark();
+
+        let mut index = 0;
+        loop {
+            let value = collect::recover(|| {
+                seq.next_element_seed(&*self.0.value)
+                    .at(|| Segment::Index(index))
+            })?;
+            match value {
+                Some(Some(value)) => items.push(value),
+                Some(None) => break,
+                None => {}
+            }
+            index += 1;
+        }
 
-        while let Some(value) = seq.next_element_seed(&*self.0.value)? {
-            items.push(value);
+        if collect::failed_since(mark) {
+            return Err(collect::failed()).de();
         }
 
         let mut list = Object::build_list(items.len()).de()?;
@@ -0,0 +1,42 @@
+use crate::{
+    decode::any::AnyVisitor,
+    error::{Convert, ErrorKind, Result},
+    object::{Object, ObjectRef},
+    schema::*,
+};
+use serde::de::{DeserializeSeed, Deserializer};
+
+impl Literal {
+    fn find(&self, data: &ObjectRef) -> Result<Option<&LiteralValue>> {
+        let ty = data.get_type()?;
+        for v in &self.values {
+            // `True == 1` in Python, so compare the types as well.
+            if v.data.get_type()?.is(ty.as_ptr()) && v.data.equals(data)? {
+                return Ok(Some(v));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for &'a Literal {
+    type Value = Object;
+
+    fn deserialize<D>(self, deserializer: D) -> std::result::Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let data = deserializer.deserialize_any(AnyVisitor)?;
+
+        match self.find(&data).de()? {
+            Some(v) => Ok(v.value.owned()),
+            None => Err(err!(
+                "the literal value must be any of {}: got {:?}",
+                self.values(),
+                data.repr().de()?
+            )
+            .with_kind(ErrorKind::InvalidValue))
+            .de(),
+        }
+    }
+}
@@ -1,15 +1,36 @@
-use crate::{decode::primitive::*, error::Convert, object::Object, schema::*};
+use crate::{
+    avro, bson, cbor, decode::primitive::*, error::Convert, object::Object, positional, schema::*,
+};
 use serde::de::{DeserializeSeed, Deserializer};
 
+/// Decodes the tagged value of CBOR as its content, for the visitors which don't use the tag.
+macro_rules! untag {
+    () => {
+        fn visit_newtype_struct<D>(
+            self,
+            deserializer: D,
+        ) -> std::result::Result<Self::Value, D::Error>
+        where
+            D: serde::de::Deserializer<'de>,
+        {
+            deserializer.deserialize_any(self)
+        }
+    };
+}
+
 pub mod any;
 pub mod class;
+pub mod constraint;
+pub mod content;
 pub mod dict;
 pub mod enums;
 pub mod frozen_set;
 pub mod list;
+pub mod literal;
 pub mod primitive;
 pub mod set;
 pub mod tuple;
+pub mod typed_dict;
 pub mod union;
 
 impl<'a, 'de> DeserializeSeed<'de> for &'a Schema {
@@ -19,13 +40,26 @@ impl<'a, 'de> DeserializeSeed<'de> for &'a Schema {
     where
         D: Deserializer<'de>,
     {
+        if avro::enabled() {
+            avro::check(self).de()?;
+        }
+
         match self {
             Schema::Bool => deserializer.deserialize_bool(BoolVisitor),
             Schema::Int => deserializer.deserialize_i64(IntVisitor),
             Schema::Float => deserializer.deserialize_f64(FloatVisitor),
+            Schema::Str | Schema::DateTime | Schema::Decimal | Schema::Uuid if bson::enabled() => {
+                bson::deserialize(self, deserializer)
+            }
             Schema::Str => deserializer.deserialize_str(StrVisitor),
             Schema::Bytes => deserializer.deserialize_bytes(BytesVisitor(false)),
             Schema::ByteArray => deserializer.deserialize_bytes(BytesVisitor(true)),
+            Schema::DateTime | Schema::Decimal | Schema::Uuid if cbor::enabled() => {
+                deserializer.deserialize_any(cbor::TaggedVisitor(self))
+            }
+            Schema::DateTime | Schema::Date | Schema::Time | Schema::Decimal if avro::enabled() => {
+                avro::deserialize(self, deserializer)
+            }
             Schema::DateTime => {
                 let s = deserializer.deserialize_str(StrVisitor)?;
                 s.into_datetime().de()
@@ -46,15 +80,36 @@ impl<'a, 'de> DeserializeSeed<'de> for &'a Schema {
                 let s = deserializer.deserialize_str(StrVisitor)?;
                 s.into_uuid().de()
             }
+            Schema::Class(_)
+            | Schema::Enum(_)
+            | Schema::Literal(_)
+            | Schema::Union(_)
+            | Schema::Tuple(Tuple { any: false, .. })
+            | Schema::Any(_)
+                if positional::enabled() =>
+            {
+                positional::deserialize(self, deserializer)
+            }
             Schema::Dict(d) => d.deserialize(deserializer),
             Schema::List(l) => l.deserialize(deserializer),
             Schema::Set(s) => s.deserialize(deserializer),
             Schema::FrozenSet(s) => s.deserialize(deserializer),
             Schema::Tuple(t) => t.deserialize(deserializer),
             Schema::Class(c) => c.deserialize(deserializer),
+            Schema::TypedDict(d) => d.deserialize(deserializer),
             Schema::Enum(e) => e.deserialize(deserializer),
             Schema::Union(u) => u.deserialize(deserializer),
+            Schema::Literal(l) => l.deserialize(deserializer),
+            Schema::Converter(c) => {
+                let v = c.schema.deserialize(deserializer)?;
+                c.from_primitive
+                    .call1(v)
+                    .map_err(|e| e.describe_exception())
+                    .context(format!("error in the converter of `{}`", c.name()))
+                    .de()
+            }
             Schema::Any(a) => a.deserialize(deserializer),
+            Schema::Ref(r) => r.resolve().de()?.deserialize(deserializer),
         }
     }
 }
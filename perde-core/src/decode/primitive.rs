@@ -1,4 +1,7 @@
-use crate::{error::Convert, object::Object};
+use crate::{
+    error::{self, Convert},
+    object::Object,
+};
 use serde::de::{self, SeqAccess, Visitor};
 use smallvec::SmallVec;
 use std::fmt;
@@ -9,9 +12,11 @@ impl<'de> Visitor<'de> for BoolVisitor {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a boolean")
+        error::expecting(f, format_args!("a boolean"))
     }
 
+    untag!();
+
     fn visit_bool<E>(self, value: bool) -> Result<Self::Value, E>
     where
         E: de::Error,
@@ -26,9 +31,11 @@ impl<'de> Visitor<'de> for IntVisitor {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "an integer")
+        error::expecting(f, format_args!("an integer"))
     }
 
+    untag!();
+
     fn visit_i8<E>(self, value: i8) -> Result<Self::Value, E>
     where
         E: de::Error,
@@ -85,9 +92,11 @@ impl<'de> Visitor<'de> for FloatVisitor {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a float")
+        error::expecting(f, format_args!("a float"))
     }
 
+    untag!();
+
     fn visit_f32<E>(self, value: f32) -> Result<Self::Value, E>
     where
         E: de::Error,
@@ -109,9 +118,11 @@ impl<'de> Visitor<'de> for StrVisitor {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a string")
+        error::expecting(f, format_args!("a string"))
     }
 
+    untag!();
+
     fn visit_char<E>(self, value: char) -> Result<Self::Value, E>
     where
         E: de::Error,
@@ -147,9 +158,11 @@ impl<'de> Visitor<'de> for BytesVisitor {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "bytes")
+        error::expecting(f, format_args!("bytes"))
     }
 
+    untag!();
+
     fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
     where
         E: de::Error,
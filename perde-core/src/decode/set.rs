@@ -1,4 +1,10 @@
-use crate::{error::Convert, object::Object, schema::*};
+use crate::{
+    collect,
+    error::{self, Convert},
+    object::Object,
+    path::{Locate, Segment},
+    schema::*,
+};
 use serde::de::{DeserializeSeed, Deserializer, SeqAccess, Visitor};
 use std::fmt;
 
@@ -8,17 +14,35 @@ impl<'a, 'de> Visitor<'de> for SetVisitor<'a> {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a set")
+        error::expecting(f, format_args!("a set"))
     }
 
+    untag!();
+
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
     where
         A: SeqAccess<'de>,
     {
         let mut set = Object::build_set().de()?;
 
-        while let Some(value) = seq.next_element_seed(&*self.0.value)? {
-            set.set(value).de()?;
+        let mark = collect::mark();
+
+        let mut index = 0;
+        loop {
+            let value = collect::recover(|| {
+                seq.next_element_seed(&*self.0.value)
+                    .at(|| Segment::Index(index))
+            })?;
+            match value {
+                Some(Some(value)) => set.set(value).de()?,
+                Some(None) => break,
+                None => {}
+            }
+            index += 1;
+        }
+
+        if collect::failed_since(mark) {
+            return Err(collect::failed()).de();
         }
 
         Ok(set.build())
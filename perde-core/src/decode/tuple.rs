@@ -1,4 +1,10 @@
-use crate::{error::Convert, object::Object, schema::*};
+use crate::{
+    collect,
+    error::{self, Convert},
+    object::Object,
+    path::{Locate, Segment},
+    schema::*,
+};
 use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, SeqAccess, Visitor};
 use smallvec::SmallVec;
 use std::fmt;
@@ -9,35 +15,48 @@ impl<'a, 'de> Visitor<'de> for TupleVisitor<'a> {
     type Value = Object;
 
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "a tuple")
+        error::expecting(f, format_args!("a tuple"))
     }
 
+    untag!();
+
     fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
     where
         A: SeqAccess<'de>,
     {
         let mut items = SmallVec::<[_; 16]>::new();
+        let mark = collect::mark();
 
-        if self.0.any {
-            while let Some(value) = seq.next_element_seed(&Schema::Any(Any))? {
-                items.push(value);
-            }
-        } else {
-            let mut args = self.0.args.iter().fuse();
-
-            loop {
-                if let Some(schema) = args.next() {
-                    match seq.next_element_seed(schema)? {
-                        Some(value) => items.push(value),
-                        None => break,
-                    }
-                } else {
-                    let _: IgnoredAny = match seq.next_element()? {
-                        Some(value) => value,
-                        None => break,
-                    };
+        let mut args = self.0.args.iter().fuse();
+        let mut index = 0;
+
+        loop {
+            let schema = if self.0.any {
+                Some(&Schema::Any(Any))
+            } else {
+                args.next()
+            };
+
+            if let Some(schema) = schema {
+                let value = collect::recover(|| {
+                    seq.next_element_seed(schema).at(|| Segment::Index(index))
+                })?;
+                match value {
+                    Some(Some(value)) => items.push(value),
+                    Some(None) => break,
+                    None => {}
                 }
+            } else {
+                let _: IgnoredAny = match seq.next_element()? {
+                    Some(value) => value,
+                    None => break,
+                };
             }
+            index += 1;
+        }
+
+        if collect::failed_since(mark) {
+            return Err(collect::failed()).de();
         }
 
         let mut tuple = Object::build_tuple(items.len()).de()?;
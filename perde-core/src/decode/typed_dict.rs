@@ -0,0 +1,83 @@
+use crate::{
+    collect,
+    error::{self, Convert, ErrorKind},
+    object::Object,
+    path::{Locate, Segment},
+    schema::*,
+};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, Visitor};
+use std::{borrow::Cow, collections::HashSet, fmt};
+
+pub struct TypedDictVisitor<'a>(pub &'a TypedDict);
+
+impl<'a, 'de> Visitor<'de> for TypedDictVisitor<'a> {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("a map"))
+    }
+
+    untag!();
+
+    fn visit_map<M>(self, mut access: M) -> Result<Self::Value, M::Error>
+    where
+        M: MapAccess<'de>,
+    {
+        let mut dict = Object::build_dict().de()?;
+        let mut found = HashSet::new();
+
+        let mark = collect::mark();
+
+        while let Some(key) = access.next_key()? {
+            let key: Cow<str> = key;
+
+            // Unknown keys are ignored like the unknown fields of classes.
+            let (name, field) = match self.0.fields.get_key_value(key.as_ref()) {
+                Some(f) => f,
+                None => {
+                    let _: IgnoredAny = access.next_value()?;
+                    continue;
+                }
+            };
+
+            let value = collect::recover(|| {
+                access
+                    .next_value_seed(&field.schema)
+                    .at(|| Segment::Field(key.to_string()))
+            })?;
+            found.insert(name.as_str());
+            if let Some(value) = value {
+                dict.set(Object::new_str(name).de()?, value).de()?;
+            }
+        }
+
+        for (name, _) in self.0.fields.iter().filter(|(_, f)| f.required) {
+            if found.contains(name.as_str()) {
+                continue;
+            }
+            let e = err!("missing field \"{}\"", name).with_kind(ErrorKind::MissingField);
+            if collect::enabled() {
+                collect::push(e.with_path(Segment::Field(name.to_string()).into()));
+                continue;
+            }
+            return Err(e).at(|| Segment::Field(name.to_string())).de();
+        }
+
+        if collect::failed_since(mark) {
+            return Err(collect::failed()).de();
+        }
+
+        Ok(dict.build())
+    }
+}
+
+impl<'a, 'de> DeserializeSeed<'de> for &'a TypedDict {
+    type Value = Object;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(TypedDictVisitor(self))
+    }
+}
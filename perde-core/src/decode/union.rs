@@ -1,23 +1,39 @@
-use crate::{decode, object::Object, schema::*};
+use crate::{
+    bson, cbor, collect,
+    decode::content::{self, Content, ContentDeserializer},
+    error::{self, Convert, ErrorKind},
+    object::Object,
+    path::{self, Locate, Segment},
+    schema::*,
+};
+use ::bson::Bson;
 use serde::de::{
-    self, DeserializeSeed, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Unexpected,
-    Visitor,
+    self,
+    value::{BorrowedBytesDeserializer, MapAccessDeserializer, SeqAccessDeserializer},
+    Deserialize, DeserializeSeed, Deserializer, IgnoredAny, IntoDeserializer, MapAccess, SeqAccess,
+    Unexpected, Visitor,
 };
-use std::fmt;
+use std::{borrow::Cow, fmt};
 
 struct UnionVisitor<'a>(&'a Union);
 
-macro_rules! find {
-    ($s:expr, $unx:expr, $($kind:tt),*) => {
-        $s.0.variants.iter().find(|s| matches!(s, $(Schema::$kind(_))|*))
-        .ok_or_else(|| de::Error::invalid_type($unx, &$s))
+/// Returns the schema which decides the shape of the input of the variant,
+/// e.g. the type converted to by the converter.
+fn shape(schema: &Schema) -> Option<&Schema> {
+    match schema.resolved().ok()? {
+        Schema::Converter(c) => shape(&c.schema),
+        Schema::Class(c) => match c.transparent_field() {
+            Some((_, f)) => shape(&f.schema),
+            None => Some(schema.resolved().ok()?),
+        },
+        s => Some(s),
     }
 }
 
-macro_rules! find_p {
-    ($s:expr, $unx:expr, $($kind:tt),*) => {
-        $s.0.variants.iter().find(|s| matches!(s, $(Schema::$kind)|*))
-            .ok_or_else(|| de::Error::invalid_type($unx, &$s))
+macro_rules! find {
+    ($s:expr, $unx:expr, $($kind:pat),*) => {
+        $s.0.variants.iter().find(|s| matches!(shape(s), $(Some($kind))|*))
+            .ok_or_else(|| error::invalid_type($unx, &$s))
     }
 }
 
@@ -27,9 +43,9 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
     fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let names: Vec<_> = self.0.variants.iter().map(|v| v.name()).collect();
         if self.0.optional {
-            write!(f, "any of {:?} or None", names)
+            error::expecting(f, format_args!("any of {:?} or None", names))
         } else {
-            write!(f, "any of {:?}", names)
+            error::expecting(f, format_args!("any of {:?}", names))
         }
     }
 
@@ -37,7 +53,7 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
     where
         E: de::Error,
     {
-        let schema = find_p!(self, Unexpected::Bool(v), Bool)?;
+        let schema = find!(self, Unexpected::Bool(v), Schema::Bool)?;
         schema.deserialize(v.into_deserializer())
     }
 
@@ -66,7 +82,7 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
     where
         E: de::Error,
     {
-        let schema = find_p!(self, Unexpected::Signed(v), Int)?;
+        let schema = find!(self, Unexpected::Signed(v), Schema::Int)?;
         schema.deserialize(v.into_deserializer())
     }
 
@@ -95,7 +111,7 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
     where
         E: de::Error,
     {
-        let schema = find_p!(self, Unexpected::Unsigned(v), Int)?;
+        let schema = find!(self, Unexpected::Unsigned(v), Schema::Int)?;
         schema.deserialize(v.into_deserializer())
     }
 
@@ -110,7 +126,7 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
     where
         E: de::Error,
     {
-        let schema = find_p!(self, Unexpected::Float(v), Float)?;
+        let schema = find!(self, Unexpected::Float(v), Schema::Float)?;
         schema.deserialize(v.into_deserializer())
     }
 
@@ -132,7 +148,18 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
     where
         E: de::Error,
     {
-        let schema = find_p!(self, Unexpected::Str(v), Str)?;
+        // The other types represented by strings are tried if there's no `str`.
+        let schema = find!(self, Unexpected::Str(v), Schema::Str).or_else(|_: E| {
+            find!(
+                self,
+                Unexpected::Str(v),
+                Schema::DateTime,
+                Schema::Date,
+                Schema::Time,
+                Schema::Decimal,
+                Schema::Uuid
+            )
+        })?;
         schema.deserialize(v.into_deserializer())
     }
 
@@ -154,13 +181,8 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
     where
         E: de::Error,
     {
-        let schema = find_p!(self, Unexpected::Bytes(v), Bytes, ByteArray)?;
-
-        match schema {
-            Schema::Bytes => decode::primitive::BytesVisitor(false).visit_borrowed_bytes(v),
-            Schema::ByteArray => decode::primitive::BytesVisitor(true).visit_borrowed_bytes(v),
-            _ => Err(de::Error::invalid_type(Unexpected::Bytes(v), &self)),
-        }
+        let schema = find!(self, Unexpected::Bytes(v), Schema::Bytes, Schema::ByteArray)?;
+        schema.deserialize(BorrowedBytesDeserializer::new(v))
     }
 
     fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
@@ -177,7 +199,7 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
         if self.0.optional {
             Ok(Object::new_none())
         } else {
-            Err(de::Error::invalid_type(Unexpected::Unit, &self))
+            Err(error::invalid_type(Unexpected::Unit, &self))
         }
     }
 
@@ -189,11 +211,11 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
             return Ok(Object::new_none());
         }
 
-        let schema = find!(self, Unexpected::Option, Union)?;
+        let schema = find!(self, Unexpected::Option, Schema::Union(_))?;
 
         match schema {
             Schema::Union(u) if u.optional => Ok(Object::new_none()),
-            _ => Err(de::Error::invalid_type(Unexpected::Option, &self)),
+            _ => Err(error::invalid_type(Unexpected::Option, &self)),
         }
     }
 
@@ -201,45 +223,328 @@ impl<'a, 'de> Visitor<'de> for UnionVisitor<'a> {
     where
         D: Deserializer<'de>,
     {
-        find!(self, Unexpected::Option, Union)?;
+        find!(self, Unexpected::Option, Schema::Union(_))?;
         deserializer.deserialize_any(UnionVisitor(self.0))
     }
 
-    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
     where
-        A: SeqAccess<'de>,
+        D: Deserializer<'de>,
     {
+        let tag = match cbor::current_tag() {
+            Some(tag) if cbor::is_known(tag) => tag,
+            // Unknown tags are ignored.
+            _ => return deserializer.deserialize_any(self),
+        };
         let schema = self
             .0
             .variants
             .iter()
-            .find(|s| matches!(s, Schema::Bytes | Schema::ByteArray | Schema::List(_) | Schema::Tuple(_) | Schema::Set(_)))
-            .ok_or_else(|| de::Error::invalid_type(Unexpected::Seq, &self))?;
+            .find(|s| matches!(shape(s), Some(s) if cbor::accepts(s, tag)))
+            .ok_or_else(|| error::invalid_type(Unexpected::NewtypeStruct, &self))?;
+        schema.deserialize(cbor::TaggedDeserializer(deserializer))
+    }
 
-        match schema {
-            Schema::Bytes => decode::primitive::BytesVisitor(false).visit_seq(seq),
-            Schema::ByteArray => decode::primitive::BytesVisitor(true).visit_seq(seq),
-            Schema::List(l) => decode::list::ListVisitor(l).visit_seq(seq),
-            Schema::Tuple(t) => decode::tuple::TupleVisitor(t).visit_seq(seq),
-            Schema::Set(s) => decode::set::SetVisitor(s).visit_seq(seq),
-            _ => unreachable!(),
-        }
+    fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let schema = find!(
+            self,
+            Unexpected::Seq,
+            Schema::Bytes,
+            Schema::ByteArray,
+            Schema::List(_),
+            Schema::Tuple(_),
+            Schema::Set(_),
+            Schema::Class(Class {
+                attr: ClassAttr { as_array: true, .. },
+                ..
+            })
+        )?;
+        schema.deserialize(SeqAccessDeserializer::new(seq))
     }
 
     fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
     where
         A: MapAccess<'de>,
     {
-        let schema = find!(self, Unexpected::Map, Dict, Class)?;
+        let schema = find!(
+            self,
+            Unexpected::Map,
+            Schema::Dict(_),
+            Schema::Class(Class {
+                attr: ClassAttr {
+                    as_array: false,
+                    ..
+                },
+                ..
+            }),
+            Schema::TypedDict(_)
+        )?;
+        schema.deserialize(MapAccessDeserializer::new(map))
+    }
+}
 
-        match schema {
-            Schema::Dict(d) => decode::dict::DictVisitor(d).visit_map(map),
-            Schema::Class(c) => decode::class::ClassVisitor(c).visit_map(map),
-            _ => unreachable!(),
+struct TaggedVisitor<'a>(&'a Union);
+
+impl<'a> TaggedVisitor<'a> {
+    fn variant<E>(&self, name: &str) -> Result<&'a Class, E>
+    where
+        E: de::Error,
+    {
+        let mut classes = self.0.variants.iter().filter_map(|v| match v.resolved() {
+            Ok(Schema::Class(c)) => Some(c),
+            _ => None,
+        });
+        match classes.find(|c| c.tag_name() == name) {
+            Some(c) => Ok(c),
+            None => Err(err!(
+                "unknown variant `{}`, expected one of {:?}",
+                name,
+                self.names()
+            )
+            .with_kind(ErrorKind::UnknownVariant))
+            .de(),
+        }
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.0
+            .variants
+            .iter()
+            .filter_map(|v| match v.resolved() {
+                Ok(Schema::Class(c)) => Some(c.tag_name()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn missing<T, E>(&self, field: &str) -> Result<T, E>
+    where
+        E: de::Error,
+    {
+        Err(err!("missing field `{}`", field).with_kind(ErrorKind::MissingField)).de()
+    }
+
+    fn visit_external<'de, A>(self, mut access: A) -> Result<Object, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let key: Cow<str> = match access.next_key()? {
+            Some(key) => key,
+            None => return Err(error::invalid_length(0, &self)),
+        };
+        let cls = self.variant(&key)?;
+        let value = access
+            .next_value_seed(cls)
+            .at(|| Segment::Field(key.to_string()))?;
+        if access.next_key::<IgnoredAny>()?.is_some() {
+            return Err(error::invalid_length(2, &self));
+        }
+        Ok(value)
+    }
+
+    fn visit_internal<'de, A>(self, mut access: A, tag: &str) -> Result<Object, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        // The tag can appear anywhere, so the other fields are buffered.
+        let mut name: Option<String> = None;
+        let mut entries = Vec::new();
+
+        while let Some(key) = access.next_key::<Content>()? {
+            match &key {
+                Content::Str(k) if k == tag => {
+                    name = Some(access.next_value().at(|| Segment::Field(tag.into()))?);
+                }
+                _ => entries.push((key, access.next_value()?)),
+            }
+        }
+
+        let name = match name {
+            Some(name) => name,
+            None => return self.missing(tag),
+        };
+        let cls = self.variant(&name)?;
+        let content = Content::Map(entries);
+        cls.deserialize(ContentDeserializer::new(&content))
+    }
+
+    fn visit_adjacent<'de, A>(
+        self,
+        mut access: A,
+        tag: &str,
+        content: &str,
+    ) -> Result<Object, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut name: Option<String> = None;
+        let mut value = None;
+        // The content is buffered only if it comes before the tag.
+        let mut buffered = None;
+
+        while let Some(key) = access.next_key()? {
+            let key: Cow<str> = key;
+
+            if key == tag {
+                name = Some(access.next_value().at(|| Segment::Field(tag.into()))?);
+            } else if key == content {
+                match &name {
+                    Some(name) => {
+                        let cls = self.variant(name)?;
+                        value = Some(
+                            access
+                                .next_value_seed(cls)
+                                .at(|| Segment::Field(content.into()))?,
+                        );
+                    }
+                    None => buffered = Some(access.next_value::<Content>()?),
+                }
+            } else {
+                let _: IgnoredAny = access.next_value()?;
+            }
+        }
+
+        let name = match name {
+            Some(name) => name,
+            None => return self.missing(tag),
+        };
+        match (value, buffered) {
+            (Some(value), _) => Ok(value),
+            (None, Some(buffered)) => self
+                .variant(&name)?
+                .deserialize(ContentDeserializer::new(&buffered))
+                .at(|| Segment::Field(content.into())),
+            (None, None) => self.missing(content),
         }
     }
 }
 
+impl<'a, 'de> Visitor<'de> for TaggedVisitor<'a> {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.optional {
+            error::expecting(
+                f,
+                format_args!("a tagged union of {:?} or None", self.names()),
+            )
+        } else {
+            error::expecting(f, format_args!("a tagged union of {:?}", self.names()))
+        }
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        if self.0.optional {
+            Ok(Object::new_none())
+        } else {
+            Err(error::invalid_type(Unexpected::Unit, &self))
+        }
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visit_unit()
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_map<A>(self, access: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        match &self.0.tagging {
+            Tagging::External => self.visit_external(access),
+            Tagging::Internal { tag } => self.visit_internal(access, tag),
+            Tagging::Adjacent { tag, content } => self.visit_adjacent(access, tag, content),
+            Tagging::Shape | Tagging::Untagged => unreachable!(),
+        }
+    }
+}
+
+impl Union {
+    /// Picks the variant by the native BSON type of the input, e.g. a datetime,
+    /// and otherwise by the shape of the input.
+    fn deserialize_bson<'de, D>(&self, deserializer: D) -> Result<Object, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Bson::deserialize(deserializer)?;
+        let native = bson::native_schema(&value);
+        let variant = native
+            .as_ref()
+            .and_then(|n| self.variants.iter().find(|s| shape(s) == Some(n)));
+        let deserializer = ::bson::Deserializer::new(value);
+        match variant {
+            Some(schema) => schema.deserialize(deserializer),
+            None => deserializer.deserialize_any(UnionVisitor(self)),
+        }
+        .map_err(de::Error::custom)
+    }
+
+    /// Returns true if the variants are discriminated by literals, i.e. there are
+    /// literals or classes with literal fields, which the shape of the input can't tell.
+    fn has_literal(&self) -> bool {
+        self.variants.iter().any(|v| match v.resolved() {
+            Ok(Schema::Literal(_)) => true,
+            Ok(Schema::Class(c)) => c
+                .fields
+                .values()
+                .chain(c.flatten_fields.values())
+                .any(|f| matches!(f.schema, Schema::Literal(_))),
+            _ => false,
+        })
+    }
+
+    /// Tries the variants in order and returns the first one that succeeds.
+    fn deserialize_untagged<'de, D>(&self, deserializer: D) -> Result<Object, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let content = match Content::deserialize(deserializer)? {
+            Content::None | Content::Unit if self.optional => return Ok(Object::new_none()),
+            Content::Some(c) => *c,
+            c => c,
+        };
+
+        for variant in &self.variants {
+            let depth = path::depth();
+            match collect::suspend(|| content::from_content(&content, variant)) {
+                Ok(v) => return Ok(v),
+                Err(_) => {
+                    // Forget the failure of the trial.
+                    path::truncate(depth);
+                    error::take_kind();
+                }
+            }
+        }
+
+        let names: Vec<_> = self.variants.iter().map(|v| v.name()).collect();
+        let kind = match self.tagging {
+            Tagging::Untagged => "untagged union",
+            _ => "union",
+        };
+        Err(err!(
+            "data did not match any variant of {} {:?}",
+            kind,
+            names
+        ))
+        .de()
+    }
+}
+
 impl<'a, 'de> DeserializeSeed<'de> for &'a Union {
     type Value = Object;
 
@@ -247,6 +552,12 @@ impl<'a, 'de> DeserializeSeed<'de> for &'a Union {
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_any(UnionVisitor(self))
+        match self.tagging {
+            Tagging::Shape if self.has_literal() => self.deserialize_untagged(deserializer),
+            Tagging::Shape if bson::enabled() => self.deserialize_bson(deserializer),
+            Tagging::Shape => deserializer.deserialize_any(UnionVisitor(self)),
+            Tagging::Untagged => self.deserialize_untagged(deserializer),
+            _ => deserializer.deserialize_any(TaggedVisitor(self)),
+        }
     }
 }
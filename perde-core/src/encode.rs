@@ -1,8 +1,13 @@
 use crate::{
     attr::AttrStr,
-    error::Convert,
-    object::ObjectRef,
-    schema::{Any, FieldSchema, Schema, WithSchema},
+    avro, bson, cbor,
+    error::{Convert, ErrorKind},
+    object::{Object, ObjectRef},
+    positional,
+    schema::{
+        static_schema, Any, Class, Dict, FieldAttr, FieldSchema, Schema, SkipIf, Tagging, Tuple,
+        TypedDict, Union, WithSchema,
+    },
 };
 use indexmap::IndexMap;
 use serde::ser::Error;
@@ -10,17 +15,59 @@ use serde::{
     ser::{SerializeMap, SerializeSeq, Serializer},
     Serialize,
 };
+use std::{cell::RefCell, collections::HashSet};
 
 lazy_static::lazy_static! {
     static ref ATTR_NAME: AttrStr = AttrStr::new("name");
     static ref ATTR_VALUE: AttrStr = AttrStr::new("value");
 }
 
+thread_local! {
+    // The containers being serialized, to detect the circular references.
+    static VISITING: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+}
+
+/// Marks the container as being serialized until dropped.
+struct Visit(usize);
+
+impl Visit {
+    fn enter(object: &ObjectRef) -> crate::error::Result<Self> {
+        let id = object.as_ptr() as usize;
+        if VISITING.with(|v| v.borrow_mut().insert(id)) {
+            Ok(Self(id))
+        } else {
+            Err(err!("circular reference detected"))
+        }
+    }
+}
+
+impl Drop for Visit {
+    fn drop(&mut self) {
+        VISITING.with(|v| v.borrow_mut().remove(&self.0));
+    }
+}
+
 impl<'a> Serialize for WithSchema<'a> {
     fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
+        // The unions and the references pass the same object to another schema,
+        // so only the schemas iterating over the object are marked.
+        let _visit = match self.schema.as_ref() {
+            Schema::List(_)
+            | Schema::Set(_)
+            | Schema::FrozenSet(_)
+            | Schema::Tuple(_)
+            | Schema::Dict(_)
+            | Schema::Class(_)
+            | Schema::TypedDict(_) => Some(Visit::enter(self.object).ser()?),
+            _ => None,
+        };
+        if avro::enabled() {
+            avro::check(self.schema.as_ref()).ser()?;
+        }
+
         match self.schema.as_ref() {
             Schema::Bool => s.serialize_bool(self.object.as_bool().ser()?),
             Schema::Int => s.serialize_i64(self.object.as_i64().ser()?),
@@ -28,11 +75,29 @@ impl<'a> Serialize for WithSchema<'a> {
             Schema::Float => s.serialize_f64(self.object.as_f64().ser()?),
             Schema::ByteArray => s.serialize_bytes(self.object.as_bytearray().ser()?),
             Schema::Bytes => s.serialize_bytes(self.object.as_bytes().ser()?),
+            Schema::DateTime | Schema::Decimal | Schema::Uuid if cbor::enabled() => {
+                cbor::serialize(self.schema.as_ref(), self.object, s)
+            }
+            Schema::DateTime | Schema::Decimal | Schema::Uuid if bson::enabled() => {
+                bson::serialize(self.schema.as_ref(), self.object, s)
+            }
+            Schema::DateTime | Schema::Date | Schema::Time | Schema::Decimal if avro::enabled() => {
+                avro::serialize(self.schema.as_ref(), self.object, s)
+            }
             Schema::DateTime => s.serialize_str(self.object.isoformat().ser()?.as_str().ser()?),
             Schema::Time => s.serialize_str(self.object.isoformat().ser()?.as_str().ser()?),
             Schema::Date => s.serialize_str(self.object.isoformat().ser()?.as_str().ser()?),
             Schema::Decimal => s.serialize_str(self.object.to_str().ser()?.as_str().ser()?),
             Schema::Uuid => s.serialize_str(self.object.to_str().ser()?.as_str().ser()?),
+            Schema::Class(_)
+            | Schema::Enum(_)
+            | Schema::Literal(_)
+            | Schema::Union(_)
+            | Schema::Tuple(Tuple { any: false, .. })
+                if positional::enabled() =>
+            {
+                positional::serialize(self.schema.as_ref(), self.object, s)
+            }
             Schema::List(l) => {
                 let len = self.object.as_list().len();
                 let mut seq = s.serialize_seq(Some(len))?;
@@ -86,20 +151,51 @@ impl<'a> Serialize for WithSchema<'a> {
                 let dict = self.object.get_dict_iter().ser()?;
                 let mut map = s.serialize_map(Some(dict.len()))?;
                 for (k, v) in dict {
+                    if avro::enabled() {
+                        avro::check_key(k).ser()?;
+                    }
                     let k = k.with_schema(&d.key);
                     let v = v.with_schema(&d.value);
                     map.serialize_entry(&k, &v)?;
                 }
                 map.end()
             }
-            Schema::Class(c) => {
-                let len = if c.flatten_dict.is_some() {
-                    None
-                } else {
-                    Some(c.ser_field_len)
-                };
-                let mut map = s.serialize_map(len)?;
-                serialize_fields(&self.object, &c.fields, &mut map)?;
+            Schema::Class(c) if c.attr.as_array => {
+                let mut seq = s.serialize_seq(c.ser_field_len)?;
+                for field in c.fields.values() {
+                    if field.attr.skip || field.attr.skip_serializing {
+                        continue;
+                    }
+                    let obj = self.object.get_attr(&field.name).ser()?;
+                    let (obj, schema) = field_object(obj, field)?;
+                    seq.serialize_element(&obj.with_schema(schema))?;
+                }
+                seq.end()
+            }
+            Schema::Class(c) => match c.transparent_field() {
+                Some((_, field)) => {
+                    let obj = self.object.get_attr(&field.name).ser()?;
+                    let (obj, schema) = field_object(obj, field)?;
+                    obj.with_schema(schema).serialize(s)
+                }
+                None => serialize_class(self.object, c, c.internal_tag(), s),
+            },
+            Schema::TypedDict(d) => {
+                let mut items = vec![];
+                for (name, field) in &d.fields {
+                    let key = Object::new_str(name).ser()?;
+                    match self.object.get_dict_item(&key) {
+                        Some(v) => items.push((name, v.owned(), field)),
+                        None if field.required => {
+                            return Err(S::Error::custom(format!("missing field \"{}\"", name)))
+                        }
+                        None => {}
+                    }
+                }
+                let mut map = s.serialize_map(Some(items.len()))?;
+                for (name, v, field) in items {
+                    map.serialize_entry(name, &v.with_schema(&field.schema))?;
+                }
                 map.end()
             }
             Schema::Enum(e) => {
@@ -114,10 +210,12 @@ impl<'a> Serialize for WithSchema<'a> {
                         None => return Err(S::Error::custom(format!("no such variant: {}", name))),
                     };
                     if e.attr.skip || e.attr.skip_serializing {
-                        return Err(S::Error::custom(format!(
+                        return Err(err!(
                             "variant `{}` is marked as `skip` and cannot be serialized",
                             name
-                        )));
+                        )
+                        .with_kind(ErrorKind::SkippedVariant))
+                        .ser();
                     }
                     s.serialize_str(&e.sername)
                 }
@@ -126,26 +224,169 @@ impl<'a> Serialize for WithSchema<'a> {
                 if self.object.is_none() && u.optional {
                     return s.serialize_none();
                 }
-                let vs = self.object.get_type().ser()?.resolve(None).ser()?;
-                let vs = match u.variants.iter().find(|v| v == &vs.as_ref()) {
-                    Some(vs) => vs,
-                    None => return Err(S::Error::custom("no such variant".to_string())),
+                let vs = &u.variants[variant_index(u, self.object).ser()?];
+                let cls = match (&u.tagging, vs.resolved().ser()?) {
+                    (Tagging::Shape, _) | (Tagging::Untagged, _) => {
+                        return self.object.with_schema(vs).serialize(s)
+                    }
+                    (_, Schema::Class(cls)) => cls,
+                    _ => return Err(S::Error::custom("tagged union must consist of classes")),
                 };
-                let v = self.object.with_schema(vs);
-                v.serialize(s)
+                match &u.tagging {
+                    Tagging::External => {
+                        let mut map = s.serialize_map(Some(1))?;
+                        map.serialize_entry(cls.tag_name(), &self.object.with_schema(vs))?;
+                        map.end()
+                    }
+                    Tagging::Adjacent { tag, content } => {
+                        let mut map = s.serialize_map(Some(2))?;
+                        map.serialize_entry(tag, cls.tag_name())?;
+                        map.serialize_entry(content, &self.object.with_schema(vs))?;
+                        map.end()
+                    }
+                    Tagging::Internal { tag } => serialize_class(self.object, cls, Some(tag), s),
+                    Tagging::Shape | Tagging::Untagged => unreachable!(),
+                }
+            }
+            Schema::Literal(l) if l.position(self.object).is_none() => Err(err!(
+                "the literal value must be any of {}: got {:?}",
+                l.values(),
+                self.object.repr().ser()?
+            )
+            .with_kind(ErrorKind::InvalidValue))
+            .ser(),
+            // `None` has no schema of its own, so serialize it here.
+            Schema::Literal(_) | Schema::Any(_) if self.object.is_none() => s.serialize_none(),
+            Schema::Literal(_) | Schema::Any(_) => {
+                self.object.resolved_object().ser()?.serialize(s)
+            }
+            Schema::Converter(c) => {
+                let v = c
+                    .to_primitive
+                    .call1(self.object.owned())
+                    .map_err(|e| e.describe_exception())
+                    .context(format!("error in the converter of `{}`", c.name()))
+                    .ser()?;
+                v.with_schema(&c.schema).serialize(s)
+            }
+            Schema::Ref(r) => self.object.with_schema(r.resolve().ser()?).serialize(s),
+        }
+    }
+}
+
+/// Returns the index of the variant of the union which the object belongs to.
+pub(crate) fn variant_index(u: &Union, object: &ObjectRef) -> crate::error::Result<usize> {
+    let ty = object.get_type()?;
+    let vs = ty.resolve(None)?;
+    // The classes are compared by the type, because the variant can be
+    // a parameterized generic class, e.g. `Page[User]`.
+    u.variants
+        .iter()
+        .position(|v| match v.resolved() {
+            Ok(Schema::Class(c)) => c.ty.is(ty.as_ptr()),
+            Ok(Schema::TypedDict(d)) => fits(d, object),
+            Ok(Schema::Literal(l)) => l.position(object).is_some(),
+            Ok(v) => v == vs.as_ref(),
+            Err(_) => false,
+        })
+        .ok_or_else(|| err!("no such variant").with_kind(ErrorKind::UnionMismatch))
+}
+
+/// Returns true if the object is a dict with the keys of the typed dict,
+/// as the typed dict has no type of its own to compare.
+fn fits(d: &TypedDict, object: &ObjectRef) -> bool {
+    if !object.is_dict_object() {
+        return false;
+    }
+    let dict = match object.get_dict_iter() {
+        Ok(dict) => dict,
+        Err(_) => return false,
+    };
+    let mut required = 0;
+    for (k, _) in dict {
+        let field = if k.is_str_object() {
+            k.as_str().ok().and_then(|k| d.fields.get(k))
+        } else {
+            None
+        };
+        match field {
+            Some(f) if f.required => required += 1,
+            Some(_) => {}
+            None => return false,
+        }
+    }
+    required == d.fields.values().filter(|f| f.required).count()
+}
+
+/// An entry of the map which a class is serialized to.
+enum Entry<'a> {
+    Field(&'a str, Object, &'a Schema),
+    /// An item of the flattened dict.
+    Item(Object, Object, &'a Dict),
+}
+
+impl<'a> Entry<'a> {
+    fn serialize_to<M>(&self, map: &mut M) -> Result<(), M::Error>
+    where
+        M: SerializeMap,
+    {
+        match self {
+            Entry::Field(k, v, schema) => map.serialize_entry(k, &v.with_schema(schema)),
+            Entry::Item(k, v, d) => {
+                map.serialize_entry(&k.with_schema(&d.key), &v.with_schema(&d.value))
             }
-            Schema::Any(_) => self.object.resolved_object().ser()?.serialize(s),
         }
     }
 }
 
-fn serialize_fields<T, E>(
+/// Serializes the class into a map, with the tag of the internally tagged union if any.
+fn serialize_class<S>(
     object: &ObjectRef,
-    fields: &IndexMap<String, FieldSchema>,
-    map: &mut T,
+    cls: &Class,
+    tag: Option<&str>,
+    s: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let extra = tag.map_or(0, |_| 1);
+
+    // The length is left unknown with the flattened dict.
+    match (cls.ser_field_len, &cls.flatten_dict) {
+        (None, None) => {
+            // The fields may be skipped by `skip_serializing_if`. Collect the entries
+            // in advance, as some formats require the length of maps.
+            let mut entries = vec![];
+            visit_fields(object, &cls.fields, &mut |e| {
+                entries.push(e);
+                Ok(())
+            })?;
+            let mut map = s.serialize_map(Some(entries.len() + extra))?;
+            if let Some(tag) = tag {
+                map.serialize_entry(tag, cls.tag_name())?;
+            }
+            for e in entries {
+                e.serialize_to(&mut map)?;
+            }
+            map.end()
+        }
+        (len, _) => {
+            let mut map = s.serialize_map(len.map(|l| l + extra))?;
+            if let Some(tag) = tag {
+                map.serialize_entry(tag, cls.tag_name())?;
+            }
+            visit_fields(object, &cls.fields, &mut |e| e.serialize_to(&mut map))?;
+            map.end()
+        }
+    }
+}
+
+fn visit_fields<'a, E>(
+    object: &ObjectRef,
+    fields: &'a IndexMap<String, FieldSchema>,
+    f: &mut dyn FnMut(Entry<'a>) -> Result<(), E>,
 ) -> Result<(), E>
 where
-    T: SerializeMap<Error = E>,
     E: serde::ser::Error,
 {
     for (_, field) in fields {
@@ -158,14 +399,12 @@ where
         if field.attr.flatten {
             match &field.schema {
                 Schema::Class(cls) => {
-                    serialize_fields(&obj, &cls.fields, map)?;
+                    visit_fields(&obj, &cls.fields, f)?;
                 }
                 Schema::Dict(d) => {
                     let dict = obj.get_dict_iter().ser()?;
                     for (k, v) in dict {
-                        let k = k.with_schema(&d.key);
-                        let v = v.with_schema(&d.value);
-                        map.serialize_entry(&k, &v)?;
+                        f(Entry::Item(k.owned(), v.owned(), d))?;
                     }
                 }
                 _ => {
@@ -174,10 +413,43 @@ where
                     ))
                 }
             }
-        } else {
-            let f = obj.with_schema(&field.schema);
-            map.serialize_entry(&field.rename, &f)?;
+        } else if !skip_serializing(&obj, &field.attr).ser()? {
+            let (obj, schema) = field_object(obj, field)?;
+            f(Entry::Field(&field.rename, obj, schema))?;
         }
     }
     Ok(())
 }
+
+/// Returns true if the value of the field meets the condition of `skip_serializing_if`.
+fn skip_serializing(obj: &ObjectRef, attr: &FieldAttr) -> crate::error::Result<bool> {
+    match attr.skip_serializing_if {
+        Some(SkipIf::None) => Ok(obj.is_none()),
+        Some(SkipIf::Default) => match (&attr.default, &attr.default_factory) {
+            (Some(d), _) => obj.equals(d),
+            (None, Some(f)) => obj.equals(&*f.call0()?),
+            (None, None) => Ok(false),
+        },
+        Some(SkipIf::Empty) => Ok(!obj.is_none() && obj.length()? == 0),
+        None => Ok(false),
+    }
+}
+
+/// Returns the value of the field to serialize, applying `perde_serialize_with` if any.
+pub(crate) fn field_object<E>(obj: Object, field: &FieldSchema) -> Result<(Object, &Schema), E>
+where
+    E: serde::ser::Error,
+{
+    match &field.attr.serialize_with {
+        Some(f) => {
+            // The type of the value returned by the hook is unknown.
+            let obj = f
+                .call1(obj)
+                .map_err(|e| e.describe_exception())
+                .context("error in `perde_serialize_with`")
+                .ser()?;
+            Ok((obj, &static_schema().any))
+        }
+        None => Ok((obj, &field.schema)),
+    }
+}
@@ -1,17 +1,163 @@
-use crate::object::ErrorObject;
-use pyo3::{type_object::PyTypeObject, PyErr, Python};
-use serde::{de, ser};
-use std::fmt::{self, Display};
+use crate::{
+    exception::{Category, Exceptions},
+    object::{ErrorObject, ObjectRef},
+    path::Path,
+};
+use pyo3::{type_object::PyTypeObject, types::PyType, IntoPy, PyErr, Python};
+use serde::{
+    de::{self, Expected, Unexpected},
+    ser,
+};
+use std::{
+    cell::Cell,
+    fmt::{self, Display},
+};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
-pub enum Error {
+pub struct Error {
+    repr: Repr,
+    kind: ErrorKind,
+    path: Path,
+    // Boxed to keep `Result<T>` small.
+    position: Option<Box<Position>>,
+    errors: Vec<Error>,
+}
+
+#[derive(Debug)]
+enum Repr {
     TypeError(String),
     ValueError(String),
     Native(String, Option<ErrorObject>),
 }
 
+/// The kind of an error, which decides the exception type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    UnsupportedType,
+    InvalidAttribute,
+    InvalidType,
+    InvalidValue,
+    InvalidLength,
+    UnknownVariant,
+    UnknownField,
+    MissingField,
+    SkippedVariant,
+    UnionMismatch,
+    Validation,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::UnsupportedType => "unsupported_type",
+            Self::InvalidAttribute => "invalid_attribute",
+            Self::InvalidType => "invalid_type",
+            Self::InvalidValue => "invalid_value",
+            Self::InvalidLength => "invalid_length",
+            Self::UnknownVariant => "unknown_variant",
+            Self::UnknownField => "unknown_field",
+            Self::MissingField => "missing_field",
+            Self::SkippedVariant => "skipped_variant",
+            Self::UnionMismatch => "union_mismatch",
+            Self::Validation => "validation",
+            Self::Other => "other",
+        }
+    }
+}
+
+thread_local! {
+    // The kind of the last error created for serde, which only keeps the message.
+    static KIND: Cell<ErrorKind> = Cell::new(ErrorKind::Other);
+}
+
+pub(crate) fn set_kind(kind: ErrorKind) {
+    KIND.with(|k| k.set(kind))
+}
+
+pub(crate) fn take_kind() -> ErrorKind {
+    KIND.with(|k| k.replace(ErrorKind::Other))
+}
+
+/// Runs the serialization or deserialization, and restores the kind of the error
+/// lost while the error went through serde.
+pub fn classify<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    take_kind();
+    f().map_err(|e| match (e.kind, take_kind()) {
+        (ErrorKind::Other, kind) => e.with_kind(kind),
+        _ => e,
+    })
+}
+
+/// Creates serde's error of an unexpected type, and records the kind.
+pub(crate) fn invalid_type<E: de::Error>(unexp: Unexpected, exp: &dyn Expected) -> E {
+    let e = E::invalid_type(unexp, exp);
+    set_kind(ErrorKind::InvalidType);
+    e
+}
+
+/// Creates serde's error of an unexpected length, and records the kind.
+pub(crate) fn invalid_length<E: de::Error>(len: usize, exp: &dyn Expected) -> E {
+    let e = E::invalid_length(len, exp);
+    set_kind(ErrorKind::InvalidLength);
+    e
+}
+
+/// Writes what a visitor expects, which serde asks for only to create the error
+/// of an unexpected type, e.g. when the format finds a list for a string.
+/// The kind is recorded here because the format creates such an error by itself.
+pub(crate) fn expecting(f: &mut fmt::Formatter, expected: fmt::Arguments) -> fmt::Result {
+    set_kind(ErrorKind::InvalidType);
+    f.write_fmt(expected)
+}
+
+/// The position in the input text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    /// One-based line number.
+    pub line: usize,
+    /// One-based column number.
+    pub column: usize,
+    /// Zero-based byte offset.
+    pub offset: usize,
+}
+
+impl Position {
+    /// Creates the position from one-based line and column in bytes.
+    pub fn from_line_column(input: &str, line: usize, column: usize) -> Self {
+        // Each of the preceding lines ends with `\n`.
+        let offset: usize = input
+            .split('\n')
+            .take(line.saturating_sub(1))
+            .map(|l| l.len() + 1)
+            .sum();
+        Self {
+            line,
+            column,
+            offset: (offset + column.saturating_sub(1)).min(input.len()),
+        }
+    }
+
+    /// Creates the position from one-based line and column, and zero-based index in characters.
+    pub fn from_char_index(input: &str, line: usize, column: usize, index: usize) -> Self {
+        let offset = input
+            .char_indices()
+            .nth(index)
+            .map(|(i, _)| i)
+            .unwrap_or_else(|| input.len());
+        Self {
+            line,
+            column,
+            offset,
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! err {
     ($($t:tt)*) => {
@@ -61,12 +207,52 @@ pub fn raise<T: PyTypeObject, U: ToString>(msg: U) {
     pyerr.restore(py);
 }
 
+fn raise_as<U: ToString>(ty: &ObjectRef, msg: U) {
+    let gil = Python::acquire_gil();
+    let py = gil.python();
+    let ty: &PyType = unsafe { py.from_borrowed_ptr(ty.as_ptr()) };
+    let pyerr = PyErr::from_type(ty, msg.to_string());
+    pyerr.restore(py);
+}
+
 impl Error {
+    fn from_repr(repr: Repr) -> Self {
+        Self {
+            repr,
+            kind: ErrorKind::Other,
+            path: Path::default(),
+            position: None,
+            errors: vec![],
+        }
+    }
+
+    /// Creates the error reporting all the collected errors.
+    pub fn aggregate(errors: Vec<Error>) -> Self {
+        let mut message = format!("found {} error(s)", errors.len());
+        for e in &errors {
+            if e.path.is_empty() {
+                message.push_str(&format!("\n  {}", e.message()));
+            } else {
+                message.push_str(&format!("\n  {}: {}", e.path, e.message()));
+            }
+        }
+        // Keep the position if the input couldn't be parsed at all.
+        let position = match errors.as_slice() {
+            [e] => e.position.clone(),
+            _ => None,
+        };
+        Self {
+            position,
+            errors,
+            ..Self::from_repr(Repr::Native(message, None))
+        }
+    }
+
     pub fn new<T>(t: T) -> Self
     where
         T: ToString,
     {
-        Self::Native(t.to_string(), ErrorObject::new())
+        Self::from_repr(Repr::Native(t.to_string(), ErrorObject::new()))
     }
 
     pub fn type_error<T>(t: T) -> Self
@@ -74,7 +260,7 @@ impl Error {
         T: ToString,
     {
         ErrorObject::clear();
-        Self::TypeError(t.to_string())
+        Self::from_repr(Repr::TypeError(t.to_string()))
     }
 
     pub fn value_error<T>(t: T) -> Self
@@ -82,33 +268,123 @@ impl Error {
         T: ToString,
     {
         ErrorObject::clear();
-        Self::ValueError(t.to_string())
+        Self::from_repr(Repr::ValueError(t.to_string()))
     }
 
-    pub fn restore_as<T: PyTypeObject>(self) {
-        match self {
-            Error::TypeError(t) => raise::<pyo3::exceptions::PyTypeError, _>(t),
-            Error::ValueError(t) => raise::<pyo3::exceptions::PyValueError, _>(t),
-            Error::Native(_, Some(t)) => {
-                t.restore();
+    /// Raises the error as the exception type decided by the kind of the error.
+    /// The errors without a specific kind are raised as the exception type of the category.
+    pub fn restore_as(mut self, exceptions: &Exceptions, category: Category) {
+        match (&mut self.repr, exceptions.get(self.kind)) {
+            // The exception raised in Python is restored as it is.
+            (Repr::Native(_, t @ Some(_)), _) => {
+                if let Some(t) = t.take() {
+                    t.restore();
+                }
             }
-            Error::Native(s, None) => raise::<T, _>(s),
+            (Repr::TypeError(m), Some(ty))
+            | (Repr::ValueError(m), Some(ty))
+            | (Repr::Native(m, None), Some(ty)) => raise_as(ty, m),
+            (Repr::TypeError(m), None) => raise::<pyo3::exceptions::PyTypeError, _>(m),
+            (Repr::ValueError(m), None) => raise::<pyo3::exceptions::PyValueError, _>(m),
+            (Repr::Native(m, None), None) => raise_as(exceptions.category(category), m),
         }
+
+        // Expose the location as `path` attribute of the raised exception.
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let pyerr = PyErr::fetch(py);
+        let path = if self.path.is_empty() {
+            py.None()
+        } else {
+            self.path.to_string().into_py(py)
+        };
+        let _ = pyerr.pvalue(py).setattr("path", path);
+
+        // Expose the position in the input text if known.
+        let position = self.position.as_deref();
+        let value = pyerr.pvalue(py);
+        let _ = value.setattr("line", position.map(|p| p.line).into_py(py));
+        let _ = value.setattr("column", position.map(|p| p.column).into_py(py));
+        let _ = value.setattr("offset", position.map(|p| p.offset).into_py(py));
+
+        // Expose the collected errors as a list of `(path, message, kind)`.
+        let errors = if self.errors.is_empty() {
+            py.None()
+        } else {
+            self.errors
+                .iter()
+                .map(|e| {
+                    let path = if e.path.is_empty() {
+                        None
+                    } else {
+                        Some(e.path.to_string())
+                    };
+                    (path, e.message().to_string(), e.kind.as_str())
+                })
+                .collect::<Vec<_>>()
+                .into_py(py)
+        };
+        let _ = pyerr.pvalue(py).setattr("errors", errors);
+        pyerr.restore(py);
+    }
+
+    /// Uses the Python exception held by the error as the message,
+    /// because the exception itself is lost when the error goes through serde.
+    pub fn describe_exception(mut self) -> Self {
+        if let Repr::Native(m, Some(e)) = &mut self.repr {
+            *m = e.describe();
+        }
+        self
     }
 
     pub fn set_message(&mut self, message: String) {
-        match self {
-            Error::TypeError(m) | Error::ValueError(m) | Error::Native(m, _) => {
+        match &mut self.repr {
+            Repr::TypeError(m) | Repr::ValueError(m) | Repr::Native(m, _) => {
                 *m = message;
             }
         }
     }
 
     pub fn message(&self) -> &str {
-        match self {
-            Self::TypeError(m) | Self::ValueError(m) | Self::Native(m, _) => &m,
+        match &self.repr {
+            Repr::TypeError(m) | Repr::ValueError(m) | Repr::Native(m, _) => m,
         }
     }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn with_path(mut self, path: Path) -> Self {
+        self.path = path;
+        self
+    }
+
+    pub fn set_path(&mut self, path: Path) {
+        self.path = path;
+    }
+
+    pub fn position(&self) -> Option<Position> {
+        self.position.as_deref().copied()
+    }
+
+    pub fn with_position(mut self, position: Position) -> Self {
+        self.position = Some(Box::new(position));
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
 }
 
 impl<T> From<T> for Error
@@ -122,13 +398,7 @@ where
 
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "{}",
-            match self {
-                Error::TypeError(m) | Error::ValueError(m) | Error::Native(m, _) => m,
-            }
-        )
+        write!(f, "{}", self.message())
     }
 }
 
@@ -154,7 +424,10 @@ impl<T> Convert<T> for Result<T> {
         E: de::Error,
         Self: Sized,
     {
-        self.map_err(|e| de::Error::custom(e.to_string()))
+        self.map_err(|e| {
+            set_kind(e.kind());
+            de::Error::custom(e.to_string())
+        })
     }
 
     fn ser<E>(self) -> std::result::Result<T, E>
@@ -162,7 +435,10 @@ impl<T> Convert<T> for Result<T> {
         E: ser::Error,
         Self: Sized,
     {
-        self.map_err(|e| ser::Error::custom(e.to_string()))
+        self.map_err(|e| {
+            set_kind(e.kind());
+            ser::Error::custom(e.to_string())
+        })
     }
 
     fn context<C>(self, context: C) -> Result<T>
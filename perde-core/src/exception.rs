@@ -0,0 +1,199 @@
+//! The exception types raised to Python.
+//!
+//! ```text
+//! perde.Error
+//! ├── perde.SchemaError
+//! │   ├── perde.UnsupportedTypeError (also TypeError)
+//! │   └── perde.InvalidAttributeError (also TypeError and ValueError)
+//! ├── perde.DecodeError
+//! │   ├── perde.MissingFieldError
+//! │   ├── perde.UnknownFieldError
+//! │   ├── perde.InvalidTypeError
+//! │   ├── perde.UnknownVariantError
+//! │   └── perde.ValidationError
+//! └── perde.EncodeError
+//!     ├── perde.SkippedVariantError
+//!     └── perde.UnionMismatchError
+//! ```
+//!
+//! Each format module has its own base, e.g. `JsonError`, and its own decode and
+//! encode exceptions which also subclass the ones above.
+
+use crate::{
+    error::{ErrorKind, Result},
+    object::{Object, ObjectRef, SyncObject},
+};
+use pyo3::{ffi::*, types::PyModule, PyResult, Python};
+use std::{collections::HashMap, ffi::CString, sync::Mutex};
+
+/// Which operation raised the error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Schema,
+    Decode,
+    Encode,
+}
+
+pub struct Exceptions {
+    pub error: SyncObject,
+    pub schema: SyncObject,
+    pub unsupported_type: SyncObject,
+    pub invalid_attribute: SyncObject,
+    pub decode: SyncObject,
+    pub missing_field: SyncObject,
+    pub unknown_field: SyncObject,
+    pub invalid_type: SyncObject,
+    pub unknown_variant: SyncObject,
+    pub validation: SyncObject,
+    pub encode: SyncObject,
+    pub skipped_variant: SyncObject,
+    pub union_mismatch: SyncObject,
+}
+
+fn new_exception(module: &str, name: &str, bases: &[&ObjectRef]) -> Result<SyncObject> {
+    let name = CString::new(format!("{}.{}", module, name))?;
+    let mut tuple = Object::build_tuple(bases.len())?;
+    for (i, base) in bases.iter().enumerate() {
+        tuple.set(i, base.owned());
+    }
+    let bases = tuple.build();
+    let ty = unsafe { PyErr_NewException(name.as_ptr(), bases.as_ptr(), std::ptr::null_mut()) };
+    Ok(Object::new(ty)?.into())
+}
+
+impl Exceptions {
+    fn new_perde() -> Result<Self> {
+        let new = |name, bases: &[&ObjectRef]| new_exception("perde", name, bases);
+        let exception = ObjectRef::new(unsafe { PyExc_Exception })?;
+        let type_error = ObjectRef::new(unsafe { PyExc_TypeError })?;
+        let value_error = ObjectRef::new(unsafe { PyExc_ValueError })?;
+
+        let error = new("Error", &[exception])?;
+        let schema = new("SchemaError", &[&error])?;
+        let decode = new("DecodeError", &[&error])?;
+        let encode = new("EncodeError", &[&error])?;
+
+        Ok(Self {
+            unsupported_type: new("UnsupportedTypeError", &[&schema, type_error])?,
+            invalid_attribute: new("InvalidAttributeError", &[&schema, type_error, value_error])?,
+            missing_field: new("MissingFieldError", &[&decode])?,
+            unknown_field: new("UnknownFieldError", &[&decode])?,
+            invalid_type: new("InvalidTypeError", &[&decode])?,
+            unknown_variant: new("UnknownVariantError", &[&decode])?,
+            validation: new("ValidationError", &[&decode])?,
+            skipped_variant: new("SkippedVariantError", &[&encode])?,
+            union_mismatch: new("UnionMismatchError", &[&encode])?,
+            error,
+            schema,
+            decode,
+            encode,
+        })
+    }
+
+    fn new_format(module: &str, name: &str) -> Result<Self> {
+        let perde = perde()?;
+        let new = |name, bases: &[&ObjectRef]| new_exception(module, name, bases);
+
+        let error = new(name, &[&perde.error])?;
+        let decode = new("DecodeError", &[&error, &perde.decode])?;
+        let encode = new("EncodeError", &[&error, &perde.encode])?;
+
+        Ok(Self {
+            schema: perde.schema.clone(),
+            unsupported_type: perde.unsupported_type.clone(),
+            invalid_attribute: perde.invalid_attribute.clone(),
+            missing_field: new("MissingFieldError", &[&decode, &perde.missing_field])?,
+            unknown_field: new("UnknownFieldError", &[&decode, &perde.unknown_field])?,
+            invalid_type: new("InvalidTypeError", &[&decode, &perde.invalid_type])?,
+            unknown_variant: new("UnknownVariantError", &[&decode, &perde.unknown_variant])?,
+            validation: new("ValidationError", &[&decode, &perde.validation])?,
+            skipped_variant: new("SkippedVariantError", &[&encode, &perde.skipped_variant])?,
+            union_mismatch: new("UnionMismatchError", &[&encode, &perde.union_mismatch])?,
+            error,
+            decode,
+            encode,
+        })
+    }
+
+    /// Returns the exception type for the kind of the error.
+    pub fn get(&self, kind: ErrorKind) -> Option<&ObjectRef> {
+        let ty = match kind {
+            ErrorKind::UnsupportedType => &self.unsupported_type,
+            ErrorKind::InvalidAttribute => &self.invalid_attribute,
+            ErrorKind::MissingField => &self.missing_field,
+            ErrorKind::UnknownField => &self.unknown_field,
+            ErrorKind::InvalidType => &self.invalid_type,
+            ErrorKind::UnknownVariant => &self.unknown_variant,
+            ErrorKind::SkippedVariant => &self.skipped_variant,
+            ErrorKind::UnionMismatch => &self.union_mismatch,
+            ErrorKind::Validation => &self.validation,
+            ErrorKind::InvalidValue | ErrorKind::InvalidLength | ErrorKind::Other => return None,
+        };
+        Some(ty)
+    }
+
+    /// Returns the exception type for the errors which don't have a specific kind.
+    pub fn category(&self, category: Category) -> &ObjectRef {
+        match category {
+            Category::Schema => &self.schema,
+            Category::Decode => &self.decode,
+            Category::Encode => &self.encode,
+        }
+    }
+
+    /// Adds the exception types to the module.
+    pub fn add_to(&self, py: Python<'_>, m: &PyModule) -> PyResult<()> {
+        let mut types = vec![&self.error];
+        // The schema errors are shared by all the formats.
+        if perde().map(|p| std::ptr::eq(self, p)).unwrap_or(false) {
+            types.extend(&[
+                &self.schema,
+                &self.unsupported_type,
+                &self.invalid_attribute,
+            ]);
+        }
+        types.extend(&[
+            &self.decode,
+            &self.missing_field,
+            &self.unknown_field,
+            &self.invalid_type,
+            &self.unknown_variant,
+            &self.validation,
+            &self.encode,
+            &self.skipped_variant,
+            &self.union_mismatch,
+        ]);
+
+        for ty in types {
+            let obj = unsafe { pyo3::PyObject::from_borrowed_ptr(py, ty.as_ptr()) };
+            m.add(ty.name(), obj)?;
+        }
+        Ok(())
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref PERDE: Result<Exceptions> = {
+        let _gil = Python::acquire_gil();
+        Exceptions::new_perde()
+    };
+
+    static ref FORMATS: Mutex<HashMap<&'static str, &'static Exceptions>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Returns the exception types of `perde` module.
+pub fn perde() -> Result<&'static Exceptions> {
+    PERDE.as_ref().map_err(|e| err!("{}", e))
+}
+
+/// Returns the exception types of the format module.
+pub fn format(module: &'static str, name: &'static str) -> Result<&'static Exceptions> {
+    let mut formats = FORMATS.lock().unwrap();
+    if let Some(e) = formats.get(module) {
+        return Ok(e);
+    }
+    let e = Box::leak(Box::new(Exceptions::new_format(module, name)?));
+    formats.insert(module, e);
+    Ok(e)
+}
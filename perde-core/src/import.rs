@@ -6,15 +6,19 @@ use pyo3::prelude::*;
 
 pub struct Import {
     pub fields: SyncObject,
+    pub get_type_hints: SyncObject,
     pub missing: SyncObject,
     pub generic_alias: SyncObject,
     pub base_generic_alias: Option<SyncObject>,
     pub union_generic_alias: Option<SyncObject>,
     pub special_generic_alias: Option<SyncObject>,
+    pub literal_generic_alias: Option<SyncObject>,
+    pub annotated_alias: Option<SyncObject>,
     pub types_generic_alias: Option<SyncObject>,
     pub type_var: SyncObject,
     pub any: SyncObject,
     pub union: SyncObject,
+    pub literal: Option<SyncObject>,
     pub tuple: SyncObject,
     pub empty_tuple: SyncObject,
     pub optional: SyncObject,
@@ -26,8 +30,12 @@ pub struct Import {
     pub datetime: SyncObject,
     pub date: SyncObject,
     pub time: SyncObject,
+    pub timezone: SyncObject,
     pub decimal: SyncObject,
     pub uuid: SyncObject,
+    pub re_compile: SyncObject,
+    pub attrs_nothing: Option<SyncObject>,
+    pub attrs_factory: Option<SyncObject>,
 }
 
 pub fn import() -> Result<&'static Import> {
@@ -67,18 +75,27 @@ lazy_static::lazy_static! {
         let datetime_ = import!("datetime")?;
         let decimal_ = import!("decimal")?;
         let uuid_ = import!("uuid")?;
+        let re_ = import!("re")?;
+        // `attrs` is optional.
+        let attrs_ = import!("attr").ok();
         let types_ = import!("types")?;
 
         let fields = getattr!(dataclasses, "fields")?;
         let missing = getattr!(dataclasses, "MISSING")?;
+        let get_type_hints = getattr!(typing, "get_type_hints")?;
         let generic_alias = getattr!(typing, "_GenericAlias")?;
         let types_generic_alias = getattr!(types_, "GenericAlias").ok();
         let union_generic_alias = getattr!(typing, "_UnionGenericAlias").ok();
         let base_generic_alias = getattr!(typing, "_BaseGenericAlias").ok();
         let special_generic_alias = getattr!(typing, "_SpecialGenericAlias").ok();
+        let literal_generic_alias = getattr!(typing, "_LiteralGenericAlias").ok();
+        // `Annotated` is available since Python 3.9.
+        let annotated_alias = getattr!(typing, "_AnnotatedAlias").ok();
         let type_var = getattr!(typing, "TypeVar")?;
         let any = getattr!(typing, "Any")?;
         let union = getattr!(typing, "Union")?;
+        // `Literal` is available since Python 3.8.
+        let literal = getattr!(typing, "Literal").ok();
         let tuple = getattr!(typing, "Tuple")?;
         let optional = getattr!(typing, "Optional")?;
         let dict = getattr!(typing, "Dict")?;
@@ -92,20 +109,28 @@ lazy_static::lazy_static! {
         let datetime = getattr!(datetime_, "datetime")?;
         let date = getattr!(datetime_, "date")?;
         let time = getattr!(datetime_, "time")?;
+        let timezone = getattr!(datetime_, "timezone")?;
         let decimal = getattr!(decimal_, "Decimal")?;
         let uuid = getattr!(uuid_, "UUID")?;
+        let re_compile = getattr!(re_, "compile")?;
+        let attrs_nothing = attrs_.and_then(|m| getattr!(m, "NOTHING").ok());
+        let attrs_factory = attrs_.and_then(|m| getattr!(m, "Factory").ok());
 
         Ok(Import {
             fields,
+            get_type_hints,
             missing,
             generic_alias,
             types_generic_alias,
             union_generic_alias,
             base_generic_alias,
             special_generic_alias,
+            literal_generic_alias,
+            annotated_alias,
             type_var,
             any,
             union,
+            literal,
             tuple,
             empty_tuple,
             optional,
@@ -117,8 +142,12 @@ lazy_static::lazy_static! {
             datetime,
             date,
             time,
+            timezone,
             decimal,
             uuid,
+            re_compile,
+            attrs_nothing,
+            attrs_factory,
         })
     };
 }
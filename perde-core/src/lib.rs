@@ -10,18 +10,27 @@ pub mod schema;
 mod decode;
 mod encode;
 
+pub mod avro;
+pub mod bson;
+pub mod cbor;
+pub mod collect;
+pub mod exception;
+pub mod mode;
+pub mod positional;
+
 pub mod args;
 mod attr;
 mod import;
 pub mod object;
+pub mod path;
 
 pub mod prelude {
     pub use crate::args::{Args, FastArgs};
-    pub use crate::error::{raise, Convert, Error, Result};
+    pub use crate::error::{raise, Convert, Error, Position, Result};
     pub use crate::object::{Object, ObjectRef};
     pub use crate::schema::{Schema, WithSchema};
     pub use crate::{
-        add_submodule, bail, bail_type_err, bail_value_err, err, exception, impl_default_methods,
+        add_submodule, bail, bail_type_err, bail_value_err, err, exceptions, impl_default_methods,
         method_fastcall, method_varargs, module, submodule, type_err, value_err,
     };
 }
@@ -6,11 +6,13 @@ pub type _PyCFunctionFastWithKeywords = unsafe extern "C" fn(
 ) -> *mut pyo3::ffi::PyObject;
 
 #[macro_export]
-macro_rules! exception {
-    ($exc:ident) => {
+macro_rules! exceptions {
+    ($exceptions:expr) => {
         |py: pyo3::Python<'_>, _: &str, m: &pyo3::types::PyModule| -> pyo3::PyResult<()> {
-            m.add(stringify!($exc), py.get_type::<$exc>()).unwrap();
-            Ok(())
+            match $exceptions {
+                Ok(e) => e.add_to(py, m),
+                Err(e) => Err(pyo3::exceptions::PyImportError::new_err(e.to_string())),
+            }
         }
     };
 }
@@ -117,34 +119,68 @@ macro_rules! module {
 
 #[macro_export]
 macro_rules! impl_default_methods {
-    ($module_name:tt, $exception_type:tt, $loads_as:ident, $loads:ident, $dumps:ident) => {
-        pyo3::create_exception!($module_name, $exception_type, pyo3::exceptions::PyException);
+    (
+        $module_name:tt,
+        $exception_type:tt,
+        $loads_as:ident,
+        $loads:ident,
+        $dumps:ident
+        $(, $method:expr)* $(,)?
+    ) => {
+        fn exceptions() -> &'static $crate::exception::Exceptions {
+            $crate::exception::format(stringify!($module_name), stringify!($exception_type))
+                .expect("exception types are created on import")
+        }
 
         pub extern "C" fn _loads_as(
             _self: *mut pyo3::ffi::PyObject,
-            args: *mut pyo3::ffi::PyObject,
+            args: *const *mut pyo3::ffi::PyObject,
+            nargs: pyo3::ffi::Py_ssize_t,
+            kwnames: *mut pyo3::ffi::PyObject,
         ) -> *mut pyo3::ffi::PyObject {
-            use pyo3::ffi::*;
-
             let inner = || {
-                let args = Args::new(args)?;
+                let args = FastArgs::new(args, nargs, kwnames);
 
-                if args.len() != 2 {
+                if args.num_args() != 2 {
                     return Err($crate::type_err!(
                         "loads_as() requires 2 positional arguments but got {}",
-                        args.len()
+                        args.num_args()
                     ));
                 }
 
+                let mut collect_errors = false;
+                if let Some(iter) = args.iter_kwargs()? {
+                    for res in iter {
+                        let (key, value) = res?;
+                        match key {
+                            "collect_errors" => {
+                                collect_errors = value.as_bool().context("invalid argument")?;
+                            }
+                            _ => {
+                                return Err($crate::type_err!(
+                                    "loads_as() got an unexpected keyword argument `{}`",
+                                    key
+                                ))
+                            }
+                        }
+                    }
+                }
+
                 let schema = args.arg(0)?.resolve(None).context("invalid argument")?;
 
-                $loads_as(schema.as_ref(), args.arg(1)?)
+                $crate::path::track(collect_errors, || {
+                    if collect_errors {
+                        $loads_as($crate::collect::Collect(schema.as_ref()), args.arg(1)?)
+                    } else {
+                        $loads_as(schema.as_ref(), args.arg(1)?)
+                    }
+                })
             };
 
-            match inner() {
+            match $crate::mode::isolated(inner) {
                 Ok(p) => p.into_ptr(),
                 Err(e) => {
-                    e.restore_as::<$exception_type>();
+                    e.restore_as(exceptions(), $crate::exception::Category::Decode);
                     std::ptr::null_mut()
                 }
             }
@@ -167,13 +203,13 @@ macro_rules! impl_default_methods {
                 let obj = args.arg(0)?;
                 let resolved = obj.resolved_object().context("invalid argument")?;
 
-                $dumps(resolved)
+                $crate::error::classify(|| $dumps(resolved))
             };
 
-            match inner() {
+            match $crate::mode::isolated(inner) {
                 Ok(p) => p.into_ptr(),
                 Err(e) => {
-                    e.restore_as::<$exception_type>();
+                    e.restore_as(exceptions(), $crate::exception::Category::Encode);
                     std::ptr::null_mut()
                 }
             }
@@ -197,10 +233,10 @@ macro_rules! impl_default_methods {
                 $loads(obj)
             };
 
-            match inner() {
+            match $crate::mode::isolated(inner) {
                 Ok(p) => p.into_ptr(),
                 Err(e) => {
-                    e.restore_as::<$exception_type>();
+                    e.restore_as(exceptions(), $crate::exception::Category::Decode);
                     std::ptr::null_mut()
                 }
             }
@@ -208,10 +244,14 @@ macro_rules! impl_default_methods {
 
         submodule!(
             $module_name,
-            exception!($exception_type),
+            exceptions!($crate::exception::format(
+                stringify!($module_name),
+                stringify!($exception_type)
+            )),
             method_varargs!(_loads, "loads", ""),
             method_varargs!(_dumps, "dumps", ""),
-            method_varargs!(_loads_as, "loads_as", "")
+            method_fastcall!(_loads_as, "loads_as", "")
+            $(, $method)*
         );
     };
 }
@@ -0,0 +1,34 @@
+//! Isolates the modes of the formats, which change the representation of some types
+//! while the format runs the encoder and the decoder, e.g. the tags of CBOR.
+//!
+//! The modes are thread-local, so they would leak into another format called from
+//! the Python code running in between, e.g. converters and `perde_serialize_with`.
+//! Every entry point runs in [`isolated`] to start with all the modes disabled.
+//!
+//! The same goes for the state of the decoder, i.e. the collected errors, the
+//! location of the error and its kind, which a nested entry point would reset.
+
+use crate::{avro, bson, cbor, collect, error, path, positional};
+
+/// Runs `f` with the modes of all the formats disabled, and restores them afterwards.
+pub fn isolated<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let cbor = cbor::replace(false);
+    let bson = bson::replace(false);
+    let positional = positional::replace(false);
+    let avro = avro::replace(false);
+    let collector = collect::replace(Default::default());
+    let segments = path::replace(Vec::new());
+    let kind = error::take_kind();
+    let res = f();
+    cbor::replace(cbor);
+    bson::replace(bson);
+    positional::replace(positional);
+    avro::replace(avro);
+    collect::replace(collector);
+    path::replace(segments);
+    error::set_kind(kind);
+    res
+}
@@ -2,7 +2,7 @@ use crate::{
     attr::AttrStr,
     error::Result,
     import::import,
-    resolve::resolve_schema,
+    resolve::{register, resolve_schema},
     schema::{Schema, WithSchema},
 };
 use pyo3::ffi::*;
@@ -70,6 +70,16 @@ impl ObjectRef {
         resolve_schema(self, attr)
     }
 
+    /// Registers the functions converting this type to and from `as_type`.
+    pub fn register(
+        &self,
+        to_primitive: &ObjectRef,
+        from_primitive: &ObjectRef,
+        as_type: &ObjectRef,
+    ) -> Result<()> {
+        register(self, to_primitive, from_primitive, as_type)
+    }
+
     pub fn resolved_object(&self) -> Result<WithSchema<'_>> {
         let schema = self.get_type()?.resolve(None)?;
         Ok(WithSchema::new(schema, self))
@@ -88,21 +98,7 @@ impl ObjectRef {
     }
 
     pub fn set_capsule<'a, T>(&self, s: &AttrStr, item: T) -> Result<&'a T> {
-        extern "C" fn destructor(p: *mut PyObject) {
-            let p = unsafe { PyCapsule_GetPointer(p, std::ptr::null_mut()) };
-            let _b = unsafe { Box::from_raw(p) };
-        }
-
-        let p = Box::new(item);
-        let p = Box::leak(p);
-
-        let obj = Object::new(unsafe {
-            PyCapsule_New(
-                p as *mut _ as *mut std::ffi::c_void,
-                std::ptr::null_mut(),
-                Some(destructor),
-            )
-        })?;
+        let (obj, p) = Object::new_capsule(item)?;
 
         if unsafe { PyObject_SetAttrString(self.as_ptr(), s.as_ptr(), obj.as_ptr()) != 0 } {
             bail!("cannot set attribute `{}`", s)
@@ -111,13 +107,48 @@ impl ObjectRef {
         }
     }
 
+    /// Looks up the capsule set by `set_capsule`. The capsules of types are
+    /// looked up only in their own `__dict__`, not inherited from the base classes.
     pub fn get_capsule<'a, T>(&self, s: &AttrStr) -> Option<&'a T> {
+        if self.is_type_object() {
+            return self.get_own_attr(s)?.as_capsule();
+        }
         if !self.has_attr(s) {
             return None;
         }
-        let obj = self.get_attr(s).ok()?;
+        self.get_attr(s).ok()?.as_capsule()
+    }
 
-        let p = unsafe { PyCapsule_GetPointer(obj.as_ptr(), std::ptr::null_mut()) };
+    /// Deletes the capsule set by `set_capsule` in the type, if any.
+    pub fn del_capsule(&self, s: &AttrStr) -> Result<()> {
+        if self.get_own_attr(s).is_none() {
+            return Ok(());
+        }
+        if unsafe { PyObject_DelAttrString(self.as_ptr(), s.as_ptr()) != 0 } {
+            bail!("cannot delete attribute `{}`", s)
+        }
+        Ok(())
+    }
+
+    /// Looks up the attribute in `__dict__` of the type without the base classes.
+    fn get_own_attr(&self, s: &AttrStr) -> Option<&ObjectRef> {
+        if !self.is_type_object() {
+            return None;
+        }
+        let dict = unsafe { (*(self.as_ptr() as *mut PyTypeObject)).tp_dict };
+        if dict.is_null() {
+            return None;
+        }
+        let p = unsafe { PyDict_GetItemString(dict, s.as_ptr()) };
+        if p.is_null() {
+            None
+        } else {
+            Self::new(p).ok()
+        }
+    }
+
+    pub fn as_capsule<'a, T>(&self) -> Option<&'a T> {
+        let p = unsafe { PyCapsule_GetPointer(self.as_ptr(), std::ptr::null_mut()) };
 
         if p.is_null() {
             None
@@ -222,6 +253,50 @@ impl ObjectRef {
         Object::new(unsafe { PyObject_Str(self.as_ptr()) })
     }
 
+    pub fn repr(&self) -> Result<Object> {
+        Object::new(unsafe { PyObject_Repr(self.as_ptr()) })
+    }
+
+    /// Compares the objects with `==`.
+    pub fn equals(&self, other: &ObjectRef) -> Result<bool> {
+        match unsafe { PyObject_RichCompareBool(self.as_ptr(), other.as_ptr(), Py_EQ) } {
+            -1 => bail!("cannot compare the objects"),
+            r => Ok(r == 1),
+        }
+    }
+
+    /// Compares the objects with `>=`.
+    pub fn ge(&self, other: &ObjectRef) -> Result<bool> {
+        match unsafe { PyObject_RichCompareBool(self.as_ptr(), other.as_ptr(), Py_GE) } {
+            -1 => bail!("cannot compare the objects"),
+            r => Ok(r == 1),
+        }
+    }
+
+    /// Compares the objects with `<=`.
+    pub fn le(&self, other: &ObjectRef) -> Result<bool> {
+        match unsafe { PyObject_RichCompareBool(self.as_ptr(), other.as_ptr(), Py_LE) } {
+            -1 => bail!("cannot compare the objects"),
+            r => Ok(r == 1),
+        }
+    }
+
+    /// Returns `len()` of the object.
+    pub fn length(&self) -> Result<usize> {
+        match unsafe { PyObject_Length(self.as_ptr()) } {
+            -1 => bail!("cannot get the length of the object"),
+            l => Ok(l as usize),
+        }
+    }
+
+    /// Returns true if the container has the item, i.e. `item in self`.
+    pub fn contains(&self, item: &ObjectRef) -> Result<bool> {
+        match unsafe { PySequence_Contains(self.as_ptr(), item.as_ptr()) } {
+            -1 => bail!("cannot check if the object contains the item"),
+            r => Ok(r == 1),
+        }
+    }
+
     pub fn is(&self, p: *mut PyObject) -> bool {
         self.as_ptr() == p
     }
@@ -234,6 +309,12 @@ impl ObjectRef {
         unsafe { (*self.as_ptr()).ob_type == &mut PyType_Type }
     }
 
+    /// Returns true if this is a type including the instances of metaclasses,
+    /// unlike `is_type` which checks if the type of this is exactly `type`.
+    pub fn is_type_object(&self) -> bool {
+        unsafe { PyType_Check(self.as_ptr()) != 0 }
+    }
+
     pub fn is_none_type(&self) -> bool {
         self.is(ptr_cast!((*Py_None()).ob_type))
     }
@@ -246,6 +327,20 @@ impl ObjectRef {
         self.is(cast!(PyUnicode_Type))
     }
 
+    /// Returns true if this is a `str` object, unlike `is_str`
+    /// which checks if this is the `str` type.
+    pub fn is_str_object(&self) -> bool {
+        unsafe { PyUnicode_Check(self.as_ptr()) != 0 }
+    }
+
+    pub fn is_callable(&self) -> bool {
+        unsafe { PyCallable_Check(self.as_ptr()) != 0 }
+    }
+
+    pub fn is_dict_object(&self) -> bool {
+        unsafe { PyDict_Check(self.as_ptr()) != 0 }
+    }
+
     pub fn is_int(&self) -> bool {
         self.is(cast!(PyLong_Type))
     }
@@ -295,10 +390,29 @@ impl ObjectRef {
                     || is_type_opt!(o, base_generic_alias)
                     || is_type_opt!(o, union_generic_alias)
                     || is_type_opt!(o, special_generic_alias)
+                    || is_type_opt!(o, literal_generic_alias)
             })
             .is_some()
     }
 
+    pub fn is_annotated(&self) -> bool {
+        self.get_type()
+            .ok()
+            .filter(|o| is_type_opt!(o, annotated_alias))
+            .is_some()
+    }
+
+    pub fn is_literal(&self) -> bool {
+        is_type_opt!(self, literal)
+    }
+
+    pub fn is_type_var(&self) -> bool {
+        self.get_type()
+            .ok()
+            .filter(|o| is_type!(o, type_var))
+            .is_some()
+    }
+
     pub fn is_builtin_generic(&self) -> bool {
         self.get_type()
             .ok()
@@ -335,7 +449,7 @@ impl ObjectRef {
 
     pub fn name(&self) -> &str {
         unsafe {
-            if self.is_type() {
+            if self.is_type_object() {
                 let p = (*(self.as_ptr() as *mut PyTypeObject)).tp_name;
                 std::ffi::CStr::from_ptr(p)
                     .to_str()
@@ -362,6 +476,47 @@ impl ObjectRef {
         objnew!(PyObject_GetAttrString(self.as_ptr(), s.as_ptr()))
     }
 
+    /// Sets the attribute in the same way as `object.__setattr__`, so that
+    /// it works with classes overriding `__setattr__`, e.g. frozen dataclasses.
+    pub fn set_attr_generic(&self, s: &AttrStr, value: Object) -> Result<()> {
+        let name = objnew!(PyUnicode_FromString(s.as_ptr()))?;
+        unsafe {
+            if PyObject_GenericSetAttr(self.as_ptr(), name.as_ptr(), value.as_ptr()) != 0 {
+                bail!("cannot set attribute `{}`", s)
+            }
+        }
+        Ok(())
+    }
+
+    /// Looks up the key in the dictionary.
+    pub fn get_dict_item(&self, key: &ObjectRef) -> Option<&ObjectRef> {
+        let p = unsafe { PyDict_GetItem(self.as_ptr(), key.as_ptr()) };
+        if p.is_null() {
+            None
+        } else {
+            Self::new(p).ok()
+        }
+    }
+
+    pub fn set_dict_item(&self, key: &ObjectRef, value: Object) -> Result<()> {
+        unsafe {
+            // This API doesn't steal.
+            if PyDict_SetItem(self.as_ptr(), key.as_ptr(), value.as_ptr()) != 0 {
+                bail!("cannot set an item to dictionary")
+            }
+        }
+        Ok(())
+    }
+
+    pub fn del_dict_item(&self, key: &ObjectRef) -> Result<()> {
+        unsafe {
+            if PyDict_DelItem(self.as_ptr(), key.as_ptr()) != 0 {
+                bail!("cannot delete an item from dictionary")
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_iter(&self) -> Result<ObjectIter> {
         Ok(ObjectIter(objnew!(PyObject_GetIter(self.as_ptr()))?))
     }
@@ -379,6 +534,10 @@ impl ObjectRef {
         objnew!(PyObject_GetItem(self.as_ptr(), key.as_ptr()))
     }
 
+    pub fn get_item(&self, key: &ObjectRef) -> Result<Object> {
+        objnew!(PyObject_GetItem(self.as_ptr(), key.as_ptr()))
+    }
+
     pub fn call(&self, args: Vec<Object>) -> Result<Object> {
         let mut tuple = Object::build_tuple(args.len())?;
         for (i, arg) in args.into_iter().enumerate() {
@@ -387,6 +546,35 @@ impl ObjectRef {
         objnew!(PyObject_CallObject(self.as_ptr(), tuple.build().as_ptr()))
     }
 
+    pub fn call_with_kwargs(
+        &self,
+        args: Vec<Object>,
+        kwargs: Vec<(&AttrStr, Object)>,
+    ) -> Result<Object> {
+        if kwargs.is_empty() {
+            return self.call(args);
+        }
+
+        let mut tuple = Object::build_tuple(args.len())?;
+        for (i, arg) in args.into_iter().enumerate() {
+            tuple.set(i, arg);
+        }
+        let dict = objnew!(PyDict_New())?;
+        for (k, v) in kwargs {
+            unsafe {
+                // This API doesn't steal.
+                if PyDict_SetItemString(dict.as_ptr(), k.as_ptr(), v.as_ptr()) != 0 {
+                    bail!("cannot set an item to dictionary")
+                }
+            }
+        }
+        objnew!(PyObject_Call(
+            self.as_ptr(),
+            tuple.build().as_ptr(),
+            dict.as_ptr()
+        ))
+    }
+
     pub fn call1(&self, obj: Object) -> Result<Object> {
         self.call(vec![obj])
     }
@@ -533,6 +721,26 @@ impl Object {
         Ok(o)
     }
 
+    pub fn new_capsule<'a, T>(item: T) -> Result<(Object, &'a T)> {
+        extern "C" fn destructor(p: *mut PyObject) {
+            let p = unsafe { PyCapsule_GetPointer(p, std::ptr::null_mut()) };
+            let _b = unsafe { Box::from_raw(p) };
+        }
+
+        let p = Box::new(item);
+        let p = Box::leak(p);
+
+        let obj = Object::new(unsafe {
+            PyCapsule_New(
+                p as *mut _ as *mut std::ffi::c_void,
+                std::ptr::null_mut(),
+                Some(destructor),
+            )
+        })?;
+
+        Ok((obj, p))
+    }
+
     pub fn new_none() -> Object {
         Self::new_clone(unsafe { Py_None() }).unwrap()
     }
@@ -593,7 +801,9 @@ impl Object {
             Schema::Time => import()?.time.call0()?,
             Schema::Decimal => import()?.decimal.call0()?,
             Schema::Uuid => import()?.uuid.call0()?,
-            Schema::Dict(_) => ObjectRef::new(cast!(PyDict_Type))?.call0()?,
+            Schema::Dict(_) | Schema::TypedDict(_) => {
+                ObjectRef::new(cast!(PyDict_Type))?.call0()?
+            }
             Schema::List(_) => ObjectRef::new(cast!(PyList_Type))?.call0()?,
             Schema::Set(_) => ObjectRef::new(cast!(PySet_Type))?.call0()?,
             Schema::FrozenSet(_) => ObjectRef::new(cast!(PyFrozenSet_Type))?.call0()?,
@@ -601,7 +811,10 @@ impl Object {
             Schema::Class(c) => c.ty.call0()?,
             Schema::Enum(_) => bail!("cannot use default construction for `enum`"),
             Schema::Union(_) => bail!("cannot use default construction for `union`"),
+            Schema::Literal(_) => bail!("cannot use default construction for `Literal`"),
+            Schema::Converter(c) => c.from_primitive.call1(Object::new_default(&c.schema)?)?,
             Schema::Any(_) => bail!("cannot use default construction for `any`"),
+            Schema::Ref(r) => Object::new_default(r.resolve()?)?,
         };
         Ok(obj)
     }
@@ -840,6 +1053,16 @@ impl DictBuilder {
         Ok(())
     }
 
+    /// Copies the items of the mapping, overwriting the existing keys.
+    pub fn merge(&mut self, other: &ObjectRef) -> Result<()> {
+        unsafe {
+            if PyDict_Merge(self.0.as_ptr(), other.as_ptr(), 1) != 0 {
+                bail!("cannot merge the items into dictionary")
+            }
+        }
+        Ok(())
+    }
+
     pub fn build(self) -> Object {
         self.0
     }
@@ -873,7 +1096,10 @@ impl SyncObject {
     }
 
     pub fn into_ptr(self) -> *mut PyObject {
-        self.as_ptr()
+        let p = self.as_ptr();
+        // The reference is moved to the caller.
+        std::mem::forget(self);
+        p
     }
 }
 
@@ -949,6 +1175,14 @@ impl ErrorObject {
         }
     }
 
+    /// Returns the exception as a text, e.g. `ValueError: message`.
+    pub fn describe(&self) -> String {
+        match self.pvalue.to_str() {
+            Ok(m) => format!("{}: {}", self.ptype.name(), m.as_str().unwrap_or("")),
+            Err(_) => self.ptype.name().to_string(),
+        }
+    }
+
     pub fn restore(self) {
         unsafe {
             pyo3::ffi::PyErr_Restore(
@@ -0,0 +1,157 @@
+use crate::{
+    collect,
+    error::{self, Error, Result},
+    object::{ErrorObject, ObjectRef},
+};
+use std::{
+    cell::RefCell,
+    fmt::{self, Display},
+};
+
+/// One step of the location in the input data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// A field of a class.
+    Field(String),
+    /// An element of a sequence.
+    Index(usize),
+    /// A value of a dictionary, with `repr()` of the key.
+    Key(String),
+}
+
+impl Segment {
+    /// Creates the segment of the value of a dictionary from the key.
+    pub fn key(key: &ObjectRef) -> Self {
+        match key.repr().and_then(|r| r.as_str().map(String::from)) {
+            Ok(repr) => Self::Key(repr),
+            Err(_) => {
+                ErrorObject::clear();
+                Self::Key("<unknown>".into())
+            }
+        }
+    }
+}
+
+/// The location in the input data, e.g. `order.items[3].price`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Path(Vec<Segment>);
+
+impl Path {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.0
+    }
+
+    /// Returns the path of `child` relative to this path.
+    pub fn join(&self, child: &Path) -> Path {
+        Path(self.0.iter().chain(child.0.iter()).cloned().collect())
+    }
+}
+
+impl From<Segment> for Path {
+    fn from(segment: Segment) -> Self {
+        Path(vec![segment])
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, s) in self.0.iter().enumerate() {
+            match s {
+                Segment::Field(name) if i == 0 => write!(f, "{}", name)?,
+                Segment::Field(name) => write!(f, ".{}", name)?,
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+                Segment::Key(key) => write!(f, "[{}]", key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    // Segments are pushed while an error unwinds the visitors,
+    // so the innermost segment comes first.
+    static PATH: RefCell<Vec<Segment>> = RefCell::new(Vec::new());
+}
+
+pub trait Locate {
+    /// Records the segment if this is an error.
+    fn at<F>(self, f: F) -> Self
+    where
+        F: FnOnce() -> Segment;
+}
+
+impl<T, E> Locate for std::result::Result<T, E> {
+    fn at<F>(self, f: F) -> Self
+    where
+        F: FnOnce() -> Segment,
+    {
+        if self.is_err() {
+            PATH.with(|p| p.borrow_mut().push(f()));
+        }
+        self
+    }
+}
+
+/// Replaces the recorded segments, e.g. to run a nested entry point.
+pub(crate) fn replace(segments: Vec<Segment>) -> Vec<Segment> {
+    PATH.with(|p| p.replace(segments))
+}
+
+pub(crate) fn clear() {
+    PATH.with(|p| p.borrow_mut().clear());
+}
+
+/// Returns the number of the recorded segments, to be passed to [`truncate`].
+pub(crate) fn depth() -> usize {
+    PATH.with(|p| p.borrow().len())
+}
+
+/// Discards the segments recorded after [`depth`] was called.
+pub(crate) fn truncate(depth: usize) {
+    PATH.with(|p| p.borrow_mut().truncate(depth));
+}
+
+pub(crate) fn take() -> Path {
+    PATH.with(|p| {
+        let mut segments = std::mem::take(&mut *p.borrow_mut());
+        segments.reverse();
+        Path(segments)
+    })
+}
+
+/// Runs the deserialization and attaches the location to the error.
+///
+/// If `collect` is true, the visitors keep going after an error and
+/// the returned error reports all the errors found in the input.
+pub fn track<T, F>(collect: bool, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    clear();
+    collect::begin(collect);
+    let res = error::classify(f);
+    let errors = collect::end();
+
+    res.map_err(|e| {
+        let path = take();
+        if !collect {
+            e.with_path(path)
+        } else if errors.is_empty() {
+            Error::aggregate(vec![e.with_path(path)])
+        } else {
+            Error::aggregate(
+                errors
+                    .into_iter()
+                    .map(|mut e| {
+                        e.set_path(path.join(e.path()));
+                        e
+                    })
+                    .collect(),
+            )
+        }
+    })
+}
@@ -0,0 +1,364 @@
+//! Encodes classes, enums and unions by position for the formats which are not self-describing.
+//!
+//! Formats such as bincode carry neither the field names nor the types of the
+//! values, so the decoder depends on the schema alone. While [`with_positional`]
+//! runs the encoder and the decoder,
+//!
+//! * classes are tuples of the fields in order, with the flattened classes inlined,
+//! * tuples of the fixed length have no length,
+//! * enums and literals are the indices of the variants,
+//! * unions are pairs of the index of the variant and the value, or just the value
+//!   if there's only one variant, wrapped with the option if `None` is allowed.
+//!
+//! `skip_serializing_if` is ignored because the decoder can't tell the skipped fields.
+//! `Any` can be encoded but can't be decoded.
+
+use crate::{
+    attr::AttrStr,
+    avro,
+    decode::{class::FieldSeed, tuple::TupleVisitor},
+    encode::{field_object, variant_index},
+    error::{self, Convert, ErrorKind},
+    object::{Object, ObjectRef},
+    path::{Locate, Segment},
+    schema::{Class, Enum, FieldSchema, Schema, Union},
+};
+use indexmap::IndexMap;
+use serde::{
+    de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor},
+    ser::{self, SerializeTuple, Serializer},
+    Deserialize, Serialize,
+};
+use std::{borrow::Cow, cell::Cell, fmt};
+
+lazy_static::lazy_static! {
+    static ref ATTR_NAME: AttrStr = AttrStr::new("name");
+}
+
+thread_local! {
+    static ENABLED: Cell<bool> = Cell::new(false);
+}
+
+/// Runs `f` with the positional encoding enabled.
+pub fn with_positional<T, F>(f: F) -> T
+where
+    F: FnOnce() -> T,
+{
+    let enabled = replace(true);
+    let res = f();
+    replace(enabled);
+    res
+}
+
+pub(crate) fn enabled() -> bool {
+    ENABLED.with(|e| e.get())
+}
+
+/// Sets the mode and returns the previous one.
+pub(crate) fn replace(enabled: bool) -> bool {
+    ENABLED.with(|e| e.replace(enabled))
+}
+
+/// Returns the fields of a class in the order of serialization, with their names on deserialization.
+pub fn fields(cls: &Class) -> error::Result<Vec<(&str, &FieldSchema)>> {
+    let mut fields = vec![];
+    collect_fields(cls, &mut fields)?;
+    Ok(fields)
+}
+
+fn collect_fields<'a>(
+    cls: &'a Class,
+    fields: &mut Vec<(&'a str, &'a FieldSchema)>,
+) -> error::Result<()> {
+    if cls.flatten_dict.is_some() {
+        bail!(
+            "flattened dict of `{}` cannot be encoded by position",
+            cls.name()
+        );
+    }
+    for (key, field) in &cls.fields {
+        if field.attr.skip || field.attr.skip_serializing {
+            continue;
+        }
+        match &field.schema {
+            Schema::Class(c) if field.attr.flatten => collect_fields(c, fields)?,
+            _ => fields.push((key, field)),
+        }
+    }
+    Ok(())
+}
+
+/// Returns the values of the fields in the order of serialization.
+fn values<'a>(
+    object: &ObjectRef,
+    cls: &'a Class,
+    values: &mut Vec<(Object, &'a FieldSchema)>,
+) -> error::Result<()> {
+    if cls.flatten_dict.is_some() {
+        bail!(
+            "flattened dict of `{}` cannot be encoded by position",
+            cls.name()
+        );
+    }
+    for field in cls.fields.values() {
+        if field.attr.skip || field.attr.skip_serializing {
+            continue;
+        }
+        let obj = object.get_attr(&field.name)?;
+        match &field.schema {
+            Schema::Class(c) if field.attr.flatten => self::values(&obj, c, values)?,
+            _ => values.push((obj, field)),
+        }
+    }
+    Ok(())
+}
+
+fn unknown_variant(index: u32) -> error::Error {
+    err!("unknown variant index `{}`", index).with_kind(ErrorKind::UnknownVariant)
+}
+
+/// Serializes a class, tuple, enum, literal or union by position.
+pub(crate) fn serialize<S>(schema: &Schema, object: &ObjectRef, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match schema {
+        Schema::Class(c) => match c.transparent_field() {
+            Some((_, field)) => {
+                let obj = object.get_attr(&field.name).ser()?;
+                let (obj, schema) = field_object(obj, field)?;
+                obj.with_schema(schema).serialize(s)
+            }
+            None => {
+                let mut vs = vec![];
+                values(object, c, &mut vs).ser()?;
+                let mut tuple = s.serialize_tuple(vs.len())?;
+                for (obj, field) in vs {
+                    let (obj, schema) = field_object(obj, field)?;
+                    tuple.serialize_element(&obj.with_schema(schema))?;
+                }
+                tuple.end()
+            }
+        },
+        Schema::Tuple(t) => {
+            let iter = object.get_tuple_iter().ser()?;
+            if iter.len() != t.args.len() {
+                return Err(err!(
+                    "expected a tuple of {} elements, got {}",
+                    t.args.len(),
+                    iter.len()
+                )
+                .with_kind(ErrorKind::InvalidLength))
+                .ser();
+            }
+            let mut tuple = s.serialize_tuple(t.args.len())?;
+            for (obj, schema) in iter.zip(t.args.iter()) {
+                tuple.serialize_element(&obj.with_schema(schema))?;
+            }
+            tuple.end()
+        }
+        Schema::Enum(e) => {
+            let name = object.get_attr(&ATTR_NAME).ser()?;
+            let name = name.as_str().ser()?;
+            let index = match e.variants.iter().position(|v| v.name == name) {
+                Some(i) => i,
+                None => return Err(ser::Error::custom(format!("no such variant: {}", name))),
+            };
+            if e.variants[index].attr.skip || e.variants[index].attr.skip_serializing {
+                return Err(err!(
+                    "variant `{}` is marked as `skip` and cannot be serialized",
+                    name
+                )
+                .with_kind(ErrorKind::SkippedVariant))
+                .ser();
+            }
+            s.serialize_u32(index as u32)
+        }
+        Schema::Literal(l) => match l.position(object) {
+            Some(i) => s.serialize_u32(i as u32),
+            None => Err(err!(
+                "the literal value must be any of {}: got {:?}",
+                l.values(),
+                object.repr().ser()?
+            )
+            .with_kind(ErrorKind::InvalidValue))
+            .ser(),
+        },
+        Schema::Union(u) if avro::enabled() => avro::serialize_union(u, object, s),
+        Schema::Union(u) if u.optional => {
+            if object.is_none() {
+                s.serialize_none()
+            } else {
+                s.serialize_some(&Variant(u, object))
+            }
+        }
+        Schema::Union(u) => Variant(u, object).serialize(s),
+        _ => Err(ser::Error::custom(format_args!(
+            "`{}` has no positional encoding",
+            schema.name()
+        ))),
+    }
+}
+
+/// The value of a union with the index of the variant.
+struct Variant<'a>(&'a Union, &'a ObjectRef);
+
+impl<'a> Serialize for Variant<'a> {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let index = variant_index(self.0, self.1).ser()?;
+        let value = self.1.with_schema(&self.0.variants[index]);
+        if self.0.variants.len() == 1 {
+            return value.serialize(s);
+        }
+        let mut tuple = s.serialize_tuple(2)?;
+        tuple.serialize_element(&(index as u32))?;
+        tuple.serialize_element(&value)?;
+        tuple.end()
+    }
+}
+
+/// Deserializes a class, tuple, enum, literal or union by position.
+pub(crate) fn deserialize<'de, D>(schema: &Schema, deserializer: D) -> Result<Object, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match schema {
+        Schema::Class(c) if c.transparent_field().is_some() => c.deserialize(deserializer),
+        Schema::Class(c) => {
+            let fs = fields(c).de()?;
+            deserializer.deserialize_tuple(fs.len(), ClassVisitor(c, fs))
+        }
+        Schema::Tuple(t) => deserializer.deserialize_tuple(t.args.len(), TupleVisitor(t)),
+        Schema::Enum(e) => {
+            let index = u32::deserialize(deserializer)?;
+            enum_variant(e, index).de()
+        }
+        Schema::Literal(l) => {
+            let index = u32::deserialize(deserializer)?;
+            match l.values.get(index as usize) {
+                Some(v) => Ok(v.value.owned()),
+                None => Err(unknown_variant(index)).de(),
+            }
+        }
+        Schema::Union(u) if avro::enabled() => avro::UnionSeed(u).deserialize(deserializer),
+        Schema::Union(u) if u.optional => deserializer.deserialize_option(OptionVisitor(u)),
+        Schema::Union(u) => VariantSeed(u).deserialize(deserializer),
+        _ => Err(err!(
+            "`{}` cannot be decoded by position as the type is unknown",
+            schema.name()
+        )
+        .with_kind(ErrorKind::UnsupportedType))
+        .de(),
+    }
+}
+
+fn enum_variant(e: &Enum, index: u32) -> error::Result<Object> {
+    let v = match e.variants.get(index as usize) {
+        Some(v) if !v.attr.skip && !v.attr.skip_deserializing => v,
+        _ => match e.variants.iter().find(|v| v.attr.other) {
+            Some(v) => v,
+            None => return Err(unknown_variant(index)),
+        },
+    };
+    e.object.get(&v.name)
+}
+
+struct ClassVisitor<'a>(&'a Class, Vec<(&'a str, &'a FieldSchema)>);
+
+impl<'a, 'de> Visitor<'de> for ClassVisitor<'a> {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("a tuple of {} fields", self.1.len()))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut map = IndexMap::new();
+        for (i, (key, field)) in self.1.iter().enumerate() {
+            let value = seq
+                .next_element_seed(FieldSeed(field))
+                .at(|| Segment::Field(key.to_string()))?;
+            let value = match value {
+                Some(v) => v,
+                None => return Err(error::invalid_length(i, &self)),
+            };
+            // The value is discarded to use the default.
+            if !field.attr.skip_deserializing {
+                map.insert(Cow::Borrowed(*key), value);
+            }
+        }
+        self.0.call(&mut map).de()
+    }
+}
+
+struct OptionVisitor<'a>(&'a Union);
+
+impl<'a, 'de> Visitor<'de> for OptionVisitor<'a> {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("an option"))
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Object::new_none())
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        VariantSeed(self.0).deserialize(deserializer)
+    }
+}
+
+struct VariantSeed<'a>(&'a Union);
+
+impl<'a, 'de> DeserializeSeed<'de> for VariantSeed<'a> {
+    type Value = Object;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match self.0.variants.as_slice() {
+            [v] => v.deserialize(deserializer),
+            _ => deserializer.deserialize_tuple(2, self),
+        }
+    }
+}
+
+impl<'a, 'de> Visitor<'de> for VariantSeed<'a> {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        error::expecting(f, format_args!("a pair of the variant index and the value"))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let index: u32 = match seq.next_element()? {
+            Some(i) => i,
+            None => return Err(error::invalid_length(0, &self)),
+        };
+        let schema = match self.0.variants.get(index as usize) {
+            Some(s) => s,
+            None => return Err(unknown_variant(index)).de(),
+        };
+        match seq.next_element_seed(schema)? {
+            Some(v) => Ok(v),
+            None => Err(error::invalid_length(1, &self)),
+        }
+    }
+}
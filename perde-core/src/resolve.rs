@@ -1,13 +1,21 @@
 use crate::{
     attr::AttrStr,
     error::Convert,
-    error::Result,
+    error::{Error, ErrorKind, Result},
     import::import,
-    object::{ObjectRef, TupleIter},
+    object::{ErrorObject, Object, ObjectRef, SyncObject, TupleIter},
     schema::*,
 };
 use indexmap::IndexMap;
-use std::{borrow::Cow, collections::HashMap};
+use std::{borrow::Cow, cell::RefCell, collections::HashMap};
+
+fn invalid_attribute(e: Error) -> Error {
+    e.with_kind(ErrorKind::InvalidAttribute)
+}
+
+fn unsupported_type(e: Error) -> Error {
+    e.with_kind(ErrorKind::UnsupportedType)
+}
 
 fn collect_members(
     mems: &IndexMap<String, FieldSchema>,
@@ -62,20 +70,128 @@ fn convert_stringcase(s: &str, case: Option<StrCase>) -> String {
     }
 }
 
+thread_local! {
+    // The classes whose resolution is in progress, to detect recursive types.
+    static RESOLVING: RefCell<Vec<usize>> = RefCell::new(Vec::new());
+}
+
+/// Marks a class as being resolved until dropped.
+struct Resolving;
+
+impl Resolving {
+    /// Returns `None` if the class is already being resolved, i.e. it refers to itself.
+    fn enter(p: &ObjectRef) -> Option<Self> {
+        let ptr = p.as_ptr() as usize;
+        RESOLVING.with(|r| {
+            let mut r = r.borrow_mut();
+            if r.contains(&ptr) {
+                None
+            } else {
+                r.push(ptr);
+                Some(Resolving)
+            }
+        })
+    }
+}
+
+impl Drop for Resolving {
+    fn drop(&mut self) {
+        RESOLVING.with(|r| r.borrow_mut().pop());
+    }
+}
+
 lazy_static::lazy_static! {
     static ref SCHEMA_CACHE: AttrStr = AttrStr::new("__perde_schema__");
     static ref DATACLASS_FIELDS: AttrStr = AttrStr::new("__dataclass_fields__");
+    static ref ATTRS_ATTRS: AttrStr = AttrStr::new("__attrs_attrs__");
+    static ref NAMED_TUPLE_FIELDS: AttrStr = AttrStr::new("_fields");
+    static ref NAMED_TUPLE_DEFAULTS: AttrStr = AttrStr::new("_field_defaults");
     static ref ATTR_NAME: AttrStr = AttrStr::new("name");
     static ref ATTR_TYPE: AttrStr = AttrStr::new("type");
     static ref ATTR_DEFAULT: AttrStr = AttrStr::new("default");
     static ref ATTR_DEFAULT_FACTORY: AttrStr = AttrStr::new("default_factory");
     static ref ATTR_METADATA: AttrStr = AttrStr::new("metadata");
+    static ref ATTR_INIT: AttrStr = AttrStr::new("init");
+    static ref ATTR_KW_ONLY: AttrStr = AttrStr::new("kw_only");
+    static ref ATTR_ALIAS: AttrStr = AttrStr::new("alias");
+    static ref ATTR_FACTORY: AttrStr = AttrStr::new("factory");
+    static ref ATTR_TAKES_SELF: AttrStr = AttrStr::new("takes_self");
+    static ref ATTR_TOTAL: AttrStr = AttrStr::new("__total__");
+    static ref ATTR_REQUIRED_KEYS: AttrStr = AttrStr::new("__required_keys__");
     static ref ATTR_VALUE: AttrStr = AttrStr::new("value");
     static ref ATTR_ARGS: AttrStr = AttrStr::new("__args__");
     static ref ATTR_ORIGIN: AttrStr = AttrStr::new("__origin__");
+    static ref ATTR_PARAMETERS: AttrStr = AttrStr::new("__parameters__");
+    static ref ATTR_SUPERTYPE: AttrStr = AttrStr::new("__supertype__");
+    static ref ATTR_ANNOTATED_METADATA: AttrStr = AttrStr::new("__metadata__");
+    static ref ATTR_INCLUDE_EXTRAS: AttrStr = AttrStr::new("include_extras");
     static ref ATTR_ENUM_METADATA: AttrStr = AttrStr::new("_perde_metadata");
     static ref ATTR_TYPENAME: AttrStr = AttrStr::new("__name__");
     static ref ATTR_DICT: AttrStr = AttrStr::new("__dict__");
+
+    static ref ATTR_MRO: AttrStr = AttrStr::new("__mro__");
+    static ref ATTR_SUBCLASSES: AttrStr = AttrStr::new("__subclasses__");
+    static ref ATTR_ORIG_BASES: AttrStr = AttrStr::new("__orig_bases__");
+
+    // The schemas of generic dataclasses for each type arguments, e.g. `Page[User]`.
+    static ref GENERIC_SCHEMAS: Result<SyncObject> = Object::build_dict().map(|d| d.build().into());
+
+    // The schemas of the types registered by `perde.register`.
+    static ref REGISTRY: Result<SyncObject> = Object::build_dict().map(|d| d.build().into());
+}
+
+/// Registers the functions converting the type to and from `as_type`, which is
+/// used for serialization instead.
+pub fn register(
+    ty: &ObjectRef,
+    to_primitive: &ObjectRef,
+    from_primitive: &ObjectRef,
+    as_type: &ObjectRef,
+) -> Result<()> {
+    if !ty.is_type() {
+        return Err(unsupported_type(type_err!("`{:?}` is not a type", ty)));
+    }
+    if !to_primitive.is_callable() || !from_primitive.is_callable() {
+        bail!("the converters of `{}` must be callable", ty.name());
+    }
+    let schema = to_schema(as_type)?;
+    let converter = Converter::new(
+        ty.owned().into(),
+        to_primitive.owned().into(),
+        from_primitive.owned().into(),
+        Box::new(schema),
+    );
+    let (capsule, _) = Object::new_capsule(Schema::Converter(converter))?;
+    let registry = REGISTRY.as_ref().map_err(|e| err!("{}", e))?;
+    registry.set_dict_item(ty, capsule)?;
+
+    // The schemas cached in the type and its subclasses are resolved again.
+    invalidate(ty)
+}
+
+fn invalidate(ty: &ObjectRef) -> Result<()> {
+    ty.del_capsule(&SCHEMA_CACHE)?;
+    for sub in ty.get_attr(&ATTR_SUBCLASSES)?.call0()?.get_iter()? {
+        invalidate(sub?.as_ref())?;
+    }
+    Ok(())
+}
+
+/// Looks up the registered schema of the type or its base classes.
+fn registered<'a>(p: &ObjectRef) -> Result<Option<Cow<'a, Schema>>> {
+    let registry = REGISTRY.as_ref().map_err(|e| err!("{}", e))?;
+    if registry.get_dict_iter()?.is_empty() || !p.has_attr(&ATTR_MRO) {
+        return Ok(None);
+    }
+    for base in p.get_attr(&ATTR_MRO)?.get_tuple_iter()? {
+        if let Some(s) = registry.get_dict_item(base) {
+            return match s.as_capsule() {
+                Some(s) => Ok(Some(Cow::Borrowed(s))),
+                None => bail!("invalid schema of registered type `{:?}`", base),
+            };
+        }
+    }
+    Ok(None)
 }
 
 pub fn resolve_schema<'a>(
@@ -104,7 +220,7 @@ pub fn resolve_schema<'a>(
         Ok(static_schema().frozenset.borrowed())
     } else if p.is_tuple() {
         Ok(static_schema().tuple.borrowed())
-    } else if p.is_none_type() || p.is_any() {
+    } else if p.is_none_type() || p.is_any() || p.is_type_var() {
         Ok(static_schema().any.borrowed())
     } else if p.is_datetime() {
         Ok(static_schema().datetime.borrowed())
@@ -119,20 +235,66 @@ pub fn resolve_schema<'a>(
     } else if p.is_builtin_generic() {
         to_generic(p).map(|s| s.owned())
     } else {
+        if p.is_generic() {
+            let origin = p.get_attr(&ATTR_ORIGIN)?;
+            if is_record(&origin) {
+                return resolve_generic_dataclass(p, &origin);
+            }
+        }
+
         if let Some(p) = p.get_capsule::<Schema>(&SCHEMA_CACHE) {
             return Ok(p.borrowed());
         }
 
-        let s = if p.has_attr(&DATACLASS_FIELDS) {
-            to_dataclass(p, &attr)?
+        // The registry is looked up by walking `__mro__`, so the result is cached.
+        // Types which don't accept attributes, e.g. extension types, are looked up every time.
+        if let Some(s) = registered(p)? {
+            return match p.set_capsule::<Schema>(&SCHEMA_CACHE, s.clone().into_owned()) {
+                Ok(cached) => Ok(cached.borrowed()),
+                Err(_) => {
+                    ErrorObject::clear();
+                    Ok(s)
+                }
+            };
+        }
+
+        // `Annotated[T, ...]` and `NewType("N", T)` are represented as `T`.
+        if p.is_annotated() {
+            // The attributes in the metadata are read only for the fields of classes,
+            // where `Annotated` is taken apart before resolving the type.
+            let metadata = p.get_attr(&ATTR_ANNOTATED_METADATA)?;
+            if metadata.get_tuple_iter()?.any(|m| m.is_dict_object()) {
+                return Err(invalid_attribute(value_err!(
+                    "the attributes in `{:?}` can be given only to the fields of classes",
+                    p
+                )));
+            }
+            return to_schema(p.get_attr(&ATTR_ORIGIN)?.as_ref()).map(Cow::Owned);
+        } else if p.has_attr(&ATTR_SUPERTYPE) {
+            return to_schema(p.get_attr(&ATTR_SUPERTYPE)?.as_ref()).map(Cow::Owned);
+        }
+
+        let s = if is_record(p) {
+            let _resolving = match Resolving::enter(p) {
+                Some(r) => r,
+                None => return Ok(Cow::Owned(Schema::Ref(ClassRef::new(p.owned().into())))),
+            };
+            let cattr = ClassAttr::parse(&attr).map_err(invalid_attribute)?;
+            to_dataclass(p, cattr, &[])?
+        } else if is_typed_dict(p) {
+            let _resolving = match Resolving::enter(p) {
+                Some(r) => r,
+                None => return Ok(Cow::Owned(Schema::Ref(ClassRef::new(p.owned().into())))),
+            };
+            to_typed_dict(p)?
         } else if p.is_generic() {
             to_generic(p)?
         } else if p.is_enum() {
             to_enum(p, &attr)?
         } else if !p.is_type() {
-            bail_type_err!("`{:?}` is not a type", p)
+            return Err(unsupported_type(type_err!("`{:?}` is not a type", p)));
         } else {
-            bail_type_err!("unsupported type `{:?}`", p)
+            return Err(unsupported_type(type_err!("unsupported type `{:?}`", p)));
         };
 
         p.set_capsule::<Schema>(&SCHEMA_CACHE, s)
@@ -144,34 +306,439 @@ fn to_schema(p: &ObjectRef) -> Result<Schema> {
     resolve_schema(p, None).map(|s| s.into_owned())
 }
 
-fn to_dataclass(p: &ObjectRef, attr: &Option<HashMap<&str, &ObjectRef>>) -> Result<Schema> {
-    let cattr = ClassAttr::parse(attr)?;
+fn resolve_generic_dataclass<'a>(p: &ObjectRef, origin: &ObjectRef) -> Result<Cow<'a, Schema>> {
+    let cache = GENERIC_SCHEMAS.as_ref().map_err(|e| err!("{}", e))?;
+
+    // `None` is put while resolving, so the references to itself become `Schema::Ref`.
+    match cache.get_dict_item(p) {
+        Some(s) if s.is_none() => {
+            return Ok(Cow::Owned(Schema::Ref(ClassRef::new(p.owned().into()))))
+        }
+        Some(s) => match s.as_capsule() {
+            Some(s) => return Ok(Cow::Borrowed(s)),
+            None => bail!("invalid schema cache of `{:?}`", p),
+        },
+        None => {}
+    }
+
+    // The class attributes given by `perde.attr` are in the schema of the origin class.
+    let cattr = match origin.get_capsule::<Schema>(&SCHEMA_CACHE) {
+        Some(Schema::Class(c)) => c.attr.clone(),
+        _ => ClassAttr::default(),
+    };
+    let params = origin.get_attr(&ATTR_PARAMETERS)?;
+    let args = p.get_attr(&ATTR_ARGS)?;
+    let typevars: Vec<_> = params
+        .get_tuple_iter()?
+        .zip(args.get_tuple_iter()?)
+        .collect();
+
+    cache.set_dict_item(p, Object::new_none())?;
+    let s = match to_dataclass(origin, cattr, &typevars) {
+        Ok(s) => s,
+        Err(e) => {
+            cache.del_dict_item(p)?;
+            return Err(e);
+        }
+    };
+    let (capsule, s) = Object::new_capsule(s)?;
+    cache.set_dict_item(p, capsule)?;
+    Ok(Cow::Borrowed(s))
+}
+
+/// Replaces the type variables in the type with the type arguments.
+fn substitute(ty: Object, typevars: &[(&ObjectRef, &ObjectRef)]) -> Result<Object> {
+    if typevars.is_empty() {
+        return Ok(ty);
+    }
+    if ty.is_type_var() {
+        return Ok(typevars
+            .iter()
+            .find(|(var, _)| var.is(ty.as_ptr()))
+            .map_or(ty, |(_, arg)| arg.owned()));
+    }
+    if !ty.has_attr(&ATTR_PARAMETERS) {
+        return Ok(ty);
+    }
+    let params = ty.get_attr(&ATTR_PARAMETERS)?;
+    let params = params.get_tuple_iter()?;
+    if params.is_empty() {
+        return Ok(ty);
+    }
+    let mut args = Object::build_tuple(params.len())?;
+    for (i, param) in params.enumerate() {
+        args.set(i, substitute(param.owned(), typevars)?);
+    }
+    ty.get_item(&args.build())
+}
 
+/// Collects the type arguments given to the generic base classes by walking
+/// `__orig_bases__`, e.g. `int` for `T` of `class IntBox(Box[int])`.
+fn base_typevars(
+    p: &ObjectRef,
+    typevars: &[(&ObjectRef, &ObjectRef)],
+) -> Result<Vec<(Object, Object)>> {
+    let mut found: Vec<(Object, Object)> = vec![];
+    if !p.has_attr(&ATTR_MRO) {
+        return Ok(found);
+    }
+    for cls in p.get_attr(&ATTR_MRO)?.get_tuple_iter()? {
+        if !cls.has_attr(&ATTR_ORIG_BASES) {
+            continue;
+        }
+        for base in cls.get_attr(&ATTR_ORIG_BASES)?.get_tuple_iter()? {
+            if !base.is_generic() {
+                continue;
+            }
+            let origin = base.get_attr(&ATTR_ORIGIN)?;
+            if !origin.has_attr(&ATTR_PARAMETERS) {
+                continue;
+            }
+            let params = origin.get_attr(&ATTR_PARAMETERS)?;
+            let args = base.get_attr(&ATTR_ARGS)?;
+            for (param, arg) in params.get_tuple_iter()?.zip(args.get_tuple_iter()?) {
+                // The subclasses come first in `__mro__`, and their arguments win.
+                let known = typevars
+                    .iter()
+                    .map(|(v, _)| *v)
+                    .chain(found.iter().map(|(v, _)| v.as_ref()))
+                    .any(|v| v.is(param.as_ptr()));
+                if known {
+                    continue;
+                }
+                // The argument can be a type variable of the subclass, e.g. `Box[List[T]]`.
+                let substitutions: Vec<_> = typevars
+                    .iter()
+                    .copied()
+                    .chain(found.iter().map(|(v, a)| (v.as_ref(), a.as_ref())))
+                    .collect();
+                let arg = substitute(arg.owned(), &substitutions)?;
+                found.push((param.owned(), arg));
+            }
+        }
+    }
+    Ok(found)
+}
+
+/// Checks if the class can be `transparent`, i.e. serialized as its only field.
+fn check_transparent(cattr: &ClassAttr, members: &IndexMap<String, FieldSchema>) -> Result<()> {
+    if cattr.as_array {
+        bail_value_err!("cannot use `transparent` together with `as_array`");
+    }
+    let mut fields = members.iter().filter(|(_, f)| !f.attr.skip);
+    match (fields.next(), fields.next()) {
+        (Some((_, f)), None) if f.attr.flatten => {
+            bail_value_err!("cannot use `perde_flatten` for the field of `transparent` class")
+        }
+        (Some(_), None) => Ok(()),
+        _ => bail_value_err!(
+            "`transparent` class must have exactly one field which is not marked `perde_skip`"
+        ),
+    }
+}
+
+/// A field of the classes represented as `Schema::Class`.
+struct RecordField {
+    name: Object,
+    /// The type used if the type hints are unavailable.
+    ty: Object,
+    default: Option<Object>,
+    default_factory: Option<Object>,
+    metadata: Option<Object>,
+    init: bool,
+    kw_only: bool,
+    /// The name of the argument of the constructor, if different from the field name.
+    alias: Option<String>,
+}
+
+/// Returns true if the type is a dataclass, an attrs class or a `NamedTuple`.
+fn is_record(p: &ObjectRef) -> bool {
+    p.has_attr(&DATACLASS_FIELDS)
+        || p.has_attr(&ATTRS_ATTRS)
+        || (p.is_type() && p.has_attr(&NAMED_TUPLE_FIELDS) && p.has_attr(&NAMED_TUPLE_DEFAULTS))
+}
+
+/// Returns true if the type is a subclass of `TypedDict`.
+fn is_typed_dict(p: &ObjectRef) -> bool {
+    p.is_type_object() && p.has_attr(&ATTR_TOTAL)
+}
+
+fn record_fields(p: &ObjectRef) -> Result<Vec<RecordField>> {
+    if p.has_attr(&DATACLASS_FIELDS) {
+        dataclass_fields(p)
+    } else if p.has_attr(&ATTRS_ATTRS) {
+        attrs_fields(p)
+    } else {
+        named_tuple_fields(p)
+    }
+}
+
+fn dataclass_fields(p: &ObjectRef) -> Result<Vec<RecordField>> {
+    let missing = &import()?.missing;
     let fields = import()?.fields.call1(p.owned())?;
-    let fields = fields.get_tuple_iter()?;
+
+    fields
+        .get_tuple_iter()?
+        .map(|field| {
+            Ok(RecordField {
+                name: field.get_attr(&ATTR_NAME)?,
+                ty: field.get_attr(&ATTR_TYPE)?,
+                default: field
+                    .get_attr(&ATTR_DEFAULT)?
+                    .into_opt()
+                    .filter(|o| !o.is(missing.as_ptr())),
+                default_factory: field
+                    .get_attr(&ATTR_DEFAULT_FACTORY)?
+                    .into_opt()
+                    .filter(|o| !o.is(missing.as_ptr())),
+                metadata: field.get_attr(&ATTR_METADATA)?.into_opt(),
+                init: field.get_attr(&ATTR_INIT)?.as_bool()?,
+                // `kw_only` is available since Python 3.10.
+                kw_only: field.has_attr(&ATTR_KW_ONLY)
+                    && field.get_attr(&ATTR_KW_ONLY)?.as_bool()?,
+                alias: None,
+            })
+        })
+        .collect()
+}
+
+fn attrs_fields(p: &ObjectRef) -> Result<Vec<RecordField>> {
+    let is = |o: &ObjectRef, t: &Option<SyncObject>| matches!(t, Some(t) if o.is(t.as_ptr()));
+    let nothing = &import()?.attrs_nothing;
+    let factory = &import()?.attrs_factory;
+    let fields = p.get_attr(&ATTRS_ATTRS)?;
+
+    fields
+        .get_tuple_iter()?
+        .map(|field| {
+            let name = field.get_attr(&ATTR_NAME)?;
+            // The type is `None` if not annotated.
+            let ty = match field.get_attr(&ATTR_TYPE)?.into_opt() {
+                Some(ty) => ty,
+                None => import()?.any.owned(),
+            };
+            let default = field
+                .get_attr(&ATTR_DEFAULT)?
+                .into_opt()
+                .filter(|o| !is(o, nothing));
+            let (default, default_factory) = match default {
+                // `attr.Factory` is given as the default.
+                Some(d) if is(d.get_type()?, factory) => {
+                    if d.get_attr(&ATTR_TAKES_SELF)?.as_bool()? {
+                        return Err(invalid_attribute(value_err!(
+                            "cannot use `Factory` with `takes_self=True` for field `{}`",
+                            name.as_str()?
+                        )));
+                    }
+                    (None, Some(d.get_attr(&ATTR_FACTORY)?))
+                }
+                d => (d, None),
+            };
+            // attrs strips the leading underscores of private fields in the constructor,
+            // which is given by `alias` since attrs 22.2.
+            let alias = if field.has_attr(&ATTR_ALIAS) {
+                field.get_attr(&ATTR_ALIAS)?.into_opt()
+            } else {
+                None
+            };
+            let alias = match alias {
+                Some(alias) => alias.as_str()?.to_string(),
+                None => name.as_str()?.trim_start_matches('_').to_string(),
+            };
+            Ok(RecordField {
+                name,
+                ty,
+                default,
+                default_factory,
+                metadata: field.get_attr(&ATTR_METADATA)?.into_opt(),
+                init: field.get_attr(&ATTR_INIT)?.as_bool()?,
+                kw_only: field.get_attr(&ATTR_KW_ONLY)?.as_bool()?,
+                alias: Some(alias),
+            })
+        })
+        .collect()
+}
+
+fn named_tuple_fields(p: &ObjectRef) -> Result<Vec<RecordField>> {
+    let names = p.get_attr(&NAMED_TUPLE_FIELDS)?;
+    let defaults = p.get_attr(&NAMED_TUPLE_DEFAULTS)?;
+
+    names
+        .get_tuple_iter()?
+        .map(|name| {
+            Ok(RecordField {
+                name: name.owned(),
+                // `collections.namedtuple` has no annotations.
+                ty: import()?.any.owned(),
+                default: defaults.get_dict_item(name).map(|d| d.owned()),
+                default_factory: None,
+                metadata: None,
+                init: true,
+                kw_only: false,
+                alias: None,
+            })
+        })
+        .collect()
+}
+
+fn type_hints(p: &ObjectRef, include_extras: bool) -> Result<Object> {
+    // The annotations are strings with `from __future__ import annotations`,
+    // or may contain forward references. `get_type_hints` evaluates them
+    // in the namespace of the module which defines the class.
+    let get_type_hints = |args| {
+        let kwargs = if include_extras {
+            vec![(&*ATTR_INCLUDE_EXTRAS, Object::new_bool(true))]
+        } else {
+            vec![]
+        };
+        import()?.get_type_hints.call_with_kwargs(args, kwargs)
+    };
+    get_type_hints(vec![p.owned()]).or_else(|e| {
+        // The class is not in the module namespace yet while the decorator runs,
+        // so give its own name to resolve the references to itself.
+        let mut localns = Object::build_dict()?;
+        localns.set(p.get_attr(&ATTR_TYPENAME)?, p.owned())?;
+        get_type_hints(vec![p.owned(), Object::new_none(), localns.build()]).map_err(|_| e)
+    })
+}
+
+fn to_typed_dict(p: &ObjectRef) -> Result<Schema> {
+    // `Required` and `NotRequired` are stripped without `include_extras`.
+    let hints = type_hints(p, false)?;
+
+    // `__required_keys__` is available since Python 3.9.
+    let required = if p.has_attr(&ATTR_REQUIRED_KEYS) {
+        Some(p.get_attr(&ATTR_REQUIRED_KEYS)?)
+    } else {
+        None
+    };
+    let total = p.get_attr(&ATTR_TOTAL)?.as_bool()?;
+
+    let mut fields = IndexMap::new();
+    for (name, ty) in hints.get_dict_iter()? {
+        let required = match &required {
+            Some(keys) => keys.contains(name)?,
+            None => total,
+        };
+        let schema = to_schema(ty)?;
+        fields.insert(
+            name.as_str()?.to_string(),
+            TypedDictField::new(schema, required),
+        );
+    }
+
+    Ok(Schema::TypedDict(TypedDict::new(
+        p.owned().into(),
+        p.name().into(),
+        fields,
+    )))
+}
+
+fn to_dataclass(
+    p: &ObjectRef,
+    cattr: ClassAttr,
+    typevars: &[(&ObjectRef, &ObjectRef)],
+) -> Result<Schema> {
+    cattr.tagging().map_err(invalid_attribute)?;
+    if cattr.as_array && cattr.skip_serializing_if.is_some() {
+        return Err(invalid_attribute(value_err!(
+            "cannot use `skip_serializing_if` together with `as_array`"
+        )));
+    }
+
+    let fields = record_fields(p)?;
+
+    // The fields inherited from the generic base classes use their type variables.
+    let inherited = base_typevars(p, typevars)?;
+    let substitutions: Vec<_> = typevars
+        .iter()
+        .copied()
+        .chain(inherited.iter().map(|(v, a)| (v.as_ref(), a.as_ref())))
+        .collect();
+
+    // Keep `Annotated` in the hints, which is available since Python 3.9.
+    let (hints, mut hints_err) = match type_hints(p, import()?.annotated_alias.is_some()) {
+        Ok(hints) => (Some(hints), None),
+        Err(e) => (None, Some(e)),
+    };
 
     let mut members = IndexMap::new();
     let mut skip_field_len = 0;
     let mut flatten_dict = None;
 
-    let missing = &import()?.missing;
-
-    for (i, field) in fields.enumerate() {
-        let name = field.get_attr(&ATTR_NAME)?;
-        let ty = field.get_attr(&ATTR_TYPE)?;
-        let default = field
-            .get_attr(&ATTR_DEFAULT)?
-            .into_opt()
-            .filter(|o| !o.is(missing.as_ptr()));
-        let default_factory = field
-            .get_attr(&ATTR_DEFAULT_FACTORY)?
-            .into_opt()
-            .filter(|o| !o.is(missing.as_ptr()));
-        let metadata = field.get_attr(&ATTR_METADATA)?.into_opt();
-
-        let fattr = FieldAttr::parse(metadata, default, default_factory)?;
+    for (i, field) in fields.into_iter().enumerate() {
+        let RecordField {
+            name,
+            ty,
+            default,
+            default_factory,
+            metadata,
+            init,
+            kw_only,
+            alias,
+        } = field;
+        let ty = match hints.as_ref().and_then(|h| h.get_dict_item(&name)) {
+            Some(ty) => ty.owned(),
+            None => ty,
+        };
+        let ty = substitute(ty, &substitutions)?;
+        let (ty, annotated) = if ty.is_annotated() {
+            let metadata = ty.get_attr(&ATTR_ANNOTATED_METADATA)?;
+            let metadata: Vec<SyncObject> = metadata
+                .get_tuple_iter()?
+                .map(|m| m.owned().into())
+                .collect();
+            (ty.get_attr(&ATTR_ORIGIN)?, metadata)
+        } else {
+            (ty, vec![])
+        };
+        if ty.is_str_object() {
+            // Raise the error, e.g. `NameError`, raised by `get_type_hints`.
+            if let Some(e) = hints_err.take() {
+                return Err(e);
+            }
+        }
+        // The attributes can be given as dictionaries in `Annotated` as well,
+        // which are overridden by `field(metadata=...)`.
+        let metadata = if annotated.iter().any(|m| m.is_dict_object()) {
+            let mut dict = Object::build_dict()?;
+            for m in annotated.iter().filter(|m| m.is_dict_object()) {
+                dict.merge(m)?;
+            }
+            if let Some(m) = &metadata {
+                dict.merge(m)?;
+            }
+            Some(dict.build())
+        } else {
+            metadata
+        };
+        let mut fattr = FieldAttr::parse(metadata, default, default_factory, init, kw_only)
+            .map_err(invalid_attribute)?;
 
         let origname = name.as_str()?;
+        if fattr.flatten && !fattr.init {
+            return Err(invalid_attribute(value_err!(
+                "cannot use `perde_flatten` for field `{}` with `init=False`",
+                origname
+            )));
+        }
+        if fattr.flatten && cattr.as_array {
+            return Err(invalid_attribute(value_err!(
+                "cannot use `perde_flatten` for field `{}` of the class with `as_array`",
+                origname
+            )));
+        }
+        if fattr.skip_serializing_if.is_some() && (fattr.flatten || cattr.as_array) {
+            return Err(invalid_attribute(value_err!(
+                "cannot use `perde_skip_serializing_if` for field `{}` with `{}`",
+                origname,
+                if fattr.flatten {
+                    "perde_flatten"
+                } else {
+                    "as_array"
+                }
+            )));
+        }
         let (dename, sename) = if let Some(renamed) = &fattr.rename {
             (renamed.to_owned(), renamed.to_owned())
         } else if cattr.rename_all.is_some() {
@@ -189,6 +756,44 @@ fn to_dataclass(p: &ObjectRef, attr: &Option<HashMap<&str, &ObjectRef>>) -> Resu
         }
 
         let schema = to_schema(ty.as_ref())?;
+        fattr
+            .constraints
+            .check(&schema)
+            .context(format!("invalid constraints of field `{}`", origname))
+            .map_err(invalid_attribute)?;
+        // The constraints are checked against the type of the field, which the value
+        // returned by the hook may not have.
+        if fattr.deserialize_with.is_some() && !fattr.constraints.is_empty() {
+            return Err(invalid_attribute(value_err!(
+                "the constraints of field `{}` cannot be used with `perde_deserialize_with`",
+                origname
+            )));
+        }
+        // The class attribute applies to the fields whose values can meet the condition,
+        // while the field attribute must be valid for the field.
+        fattr.skip_serializing_if = match (fattr.skip_serializing_if, cattr.skip_serializing_if) {
+            (Some(c), _) if !c.applies_to(&fattr, &schema) => {
+                let requirement = match c {
+                    SkipIf::None => "an optional type",
+                    SkipIf::Default => "a default value",
+                    SkipIf::Empty => "a collection or string type",
+                };
+                return Err(invalid_attribute(value_err!(
+                    "`perde_skip_serializing_if` of field `{}` requires {}",
+                    origname,
+                    requirement
+                )));
+            }
+            (Some(c), _) => Some(c),
+            (None, Some(c)) if !fattr.flatten && c.applies_to(&fattr, &schema) => Some(c),
+            _ => None,
+        };
+        let schema = match fattr.tagging().map_err(invalid_attribute)? {
+            Some(tagging) => with_tagging(schema, tagging)
+                .context(format!("invalid tagging of field `{}`", origname))
+                .map_err(invalid_attribute)?,
+            None => schema,
+        };
 
         // Setup flatten dict which absorbs all the remaining fields.
         if fattr.flatten {
@@ -200,35 +805,81 @@ fn to_dataclass(p: &ObjectRef, attr: &Option<HashMap<&str, &ObjectRef>>) -> Resu
         }
 
         // `sename` is used for serialization.
-        let mem = FieldSchema::new(AttrStr::new(origname), sename, i as usize, fattr, schema);
+        let mem = FieldSchema::new(
+            AttrStr::new(origname),
+            AttrStr::new(alias.as_deref().unwrap_or(origname)),
+            sename,
+            i as usize,
+            fattr,
+            schema,
+            annotated,
+        );
 
         // `dename` is for look up schema on deserialization.
         members.insert(dename, mem);
     }
 
+    if cattr.transparent {
+        check_transparent(&cattr, &members).map_err(invalid_attribute)?;
+    }
+
     let name = p.name();
     let class = p.owned();
     let (flatten_members, flatten_skip_len) = collect_flatten_members(&members);
 
-    let ser_field_len = if flatten_members.is_empty() {
-        members.len() - skip_field_len
+    // The aliases are looked up in the same map as the field names.
+    let fields = if flatten_members.is_empty() {
+        &members
+    } else {
+        &flatten_members
+    };
+    let mut aliases = HashMap::new();
+    for (key, field) in fields {
+        for alias in &field.attr.aliases {
+            if alias == key {
+                return Err(invalid_attribute(value_err!(
+                    "alias `{}` of field `{}` is the same as the field name",
+                    alias,
+                    key
+                )));
+            }
+            if fields.contains_key(alias) || aliases.insert(alias.clone(), key.clone()).is_some() {
+                return Err(invalid_attribute(value_err!(
+                    "alias `{}` of field `{}` is used by another field",
+                    alias,
+                    key
+                )));
+            }
+        }
+    }
+
+    let skip_if = members
+        .values()
+        .chain(flatten_members.values())
+        .any(|f| f.attr.skip_serializing_if.is_some());
+    let ser_field_len = if flatten_dict.is_some() || skip_if {
+        None
+    } else if flatten_members.is_empty() {
+        Some(members.len() - skip_field_len)
     } else {
-        flatten_members.len() - flatten_skip_len
+        Some(flatten_members.len() - flatten_skip_len)
     };
 
     Ok(Schema::Class(Class::new(
         class.into(),
         name.into(),
+        typevars.iter().map(|(_, arg)| arg.owned().into()).collect(),
         cattr,
         members,
         flatten_members,
+        aliases,
         flatten_dict,
         ser_field_len,
     )))
 }
 
 fn to_enum(p: &ObjectRef, attr: &Option<HashMap<&str, &ObjectRef>>) -> Result<Schema> {
-    let eattr = EnumAttr::parse(&attr)?;
+    let eattr = EnumAttr::parse(&attr).map_err(invalid_attribute)?;
 
     let iter = p.get_iter()?;
 
@@ -240,7 +891,7 @@ fn to_enum(p: &ObjectRef, attr: &Option<HashMap<&str, &ObjectRef>>) -> Result<Sc
 
             let attr = if item.has_attr(&ATTR_ENUM_METADATA) {
                 let metadata = item.get_attr(&ATTR_ENUM_METADATA)?;
-                VariantAttr::parse(&Some(&metadata))?
+                VariantAttr::parse(&Some(&metadata)).map_err(invalid_attribute)?
             } else {
                 VariantAttr::default()
             };
@@ -292,7 +943,108 @@ fn to_union(args: &ObjectRef) -> Result<Schema> {
         })
         .collect();
 
-    Ok(Schema::Union(Union::new(variants?, optional)))
+    let variants = variants?;
+
+    // The tagging can be set to all the variant classes instead of the field.
+    let mut tagging = None;
+    for v in &variants {
+        let t = match v {
+            Schema::Class(c) => c.attr.tagging().map_err(invalid_attribute)?,
+            _ => continue,
+        };
+        if tagging.is_some() && tagging != Some(t.clone()) {
+            return Err(invalid_attribute(value_err!(
+                "all the variants of the union must have the same tagging"
+            )));
+        }
+        tagging = Some(t);
+    }
+
+    let union = Schema::Union(Union::new(variants, optional, Tagging::Shape));
+    match tagging.flatten() {
+        Some(tagging) => with_tagging(union, tagging).map_err(invalid_attribute),
+        None => Ok(union),
+    }
+}
+
+fn with_tagging(schema: Schema, tagging: Tagging) -> Result<Schema> {
+    let mut union = match schema {
+        Schema::Union(u) => u,
+        _ => bail_type_err!("tagging can be used only with union"),
+    };
+    if tagging.is_tagged()
+        && union
+            .variants
+            .iter()
+            .any(|v| !matches!(v, Schema::Class(_) | Schema::Ref(_)))
+    {
+        bail_type_err!("all the variants of the tagged union must be classes");
+    }
+    match &tagging {
+        Tagging::Internal { tag } => {
+            // The tag is put in the map of the fields, which transparent classes don't have.
+            for v in &union.variants {
+                match v {
+                    Schema::Class(c) if c.transparent_field().is_some() => bail_value_err!(
+                        "cannot use `transparent` class `{}` in the internally tagged union",
+                        c.name
+                    ),
+                    Schema::Class(c) => check_tag(c, tag)?,
+                    _ => {}
+                }
+            }
+        }
+        Tagging::Adjacent { tag, content } if tag == content => {
+            bail_value_err!("`tag` and `content` must be different: `{}`", tag)
+        }
+        _ => {}
+    }
+    union.tagging = tagging;
+    Ok(Schema::Union(union))
+}
+
+/// Checks that the tag doesn't conflict with the fields of the class.
+fn check_tag(cls: &Class, tag: &str) -> Result<()> {
+    let fields = cls.fields.iter().chain(cls.flatten_fields.iter());
+    for (name, field) in fields {
+        if field.attr.skip || field.attr.flatten {
+            continue;
+        }
+        if name == tag || field.rename == tag {
+            bail_value_err!(
+                "tag `{}` conflicts with field `{}` of class `{}`",
+                tag,
+                name,
+                cls.name
+            );
+        }
+    }
+    Ok(())
+}
+
+fn to_literal(args: &ObjectRef) -> Result<Schema> {
+    let values: Result<_> = args
+        .get_tuple_iter()?
+        .map(|value| {
+            // The enum members appear in the data in the same way as the enum.
+            let ty = value.get_type()?;
+            let data = match to_schema(ty)? {
+                Schema::Enum(e) if e.attr.as_value => value.get_attr(&ATTR_VALUE)?,
+                Schema::Enum(e) => {
+                    let name = value.get_attr(&ATTR_NAME)?;
+                    let name = name.as_str()?;
+                    match e.variants.iter().find(|v| v.name == name) {
+                        Some(v) => Object::new_str(&v.sername)?,
+                        None => bail!("no such variant: {}", name),
+                    }
+                }
+                _ => value.owned(),
+            };
+            Ok(LiteralValue::new(value.owned().into(), data.into()))
+        })
+        .collect();
+
+    Ok(Schema::Literal(Literal::new(values?)))
 }
 
 fn to_tuple(args: &ObjectRef) -> Result<Schema> {
@@ -301,7 +1053,7 @@ fn to_tuple(args: &ObjectRef) -> Result<Schema> {
     if args.len() == 1 {
         let p = args
             .next()
-            .ok_or_else(|| type_err!("cannot get element type"))?;
+            .ok_or_else(|| unsupported_type(type_err!("cannot get element type")))?;
         if p.is(import()?.empty_tuple.as_ptr()) {
             return Ok(Schema::Tuple(Tuple::new(vec![])));
         }
@@ -360,8 +1112,13 @@ fn to_generic(p: &ObjectRef) -> Result<Schema> {
         to_list(&args)
     } else if origin.is_frozen_set() {
         to_frozen_set(&args)
+    } else if origin.is_literal() {
+        to_literal(&args)
     } else {
-        bail_type_err!("unsupported generic type: {:?}", p);
+        return Err(unsupported_type(type_err!(
+            "unsupported generic type: {:?}",
+            p
+        )));
     };
 
     s.context(format!("cannot get generic type information: `{:?}`", p))
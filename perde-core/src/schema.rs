@@ -1,6 +1,7 @@
 use crate::{
     attr::AttrStr,
     error::{Convert, Error, Result},
+    import::import,
     object::{Object, ObjectRef, SyncObject},
 };
 use derive_new::new;
@@ -37,6 +38,41 @@ impl FromStr for StrCase {
     }
 }
 
+/// The condition of `skip_serializing_if`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SkipIf {
+    /// The value is `None`.
+    None,
+    /// The value equals the default, or the result of the default factory.
+    Default,
+    /// The value is an empty collection or string.
+    Empty,
+}
+
+impl FromStr for SkipIf {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(SkipIf::None),
+            "default" => Ok(SkipIf::Default),
+            "empty" => Ok(SkipIf::Empty),
+            c => bail_value_err!("invalid condition: `{}`", c),
+        }
+    }
+}
+
+impl SkipIf {
+    /// Returns true if the values of the field can meet the condition.
+    pub fn applies_to(self, attr: &FieldAttr, schema: &Schema) -> bool {
+        match self {
+            SkipIf::None => schema.is_optional(),
+            SkipIf::Default => attr.default.is_some() || attr.default_factory.is_some(),
+            SkipIf::Empty => schema.all_variants(&sized),
+        }
+    }
+}
+
 macro_rules! field_extract_bool {
     ($dict:expr, $field:expr) => {
         $dict
@@ -62,7 +98,34 @@ macro_rules! field_extract_str {
     };
 }
 
-macro_rules! extract_stringcase {
+macro_rules! field_extract_callable {
+    ($dict:expr, $field:expr) => {
+        $dict
+            .as_ref()
+            .and_then(|map| map.get($field).ok())
+            .map(|v| {
+                if v.is_callable() {
+                    Ok(v.into())
+                } else {
+                    bail_type_err!("expected a callable got `{}`: {:?}", v.typename(), v)
+                }
+            })
+            .transpose()
+            .context(format!("invalid attribute `{}`", $field))?
+    };
+}
+
+macro_rules! field_extract_parse {
+    ($dict:expr, $field:expr) => {
+        $dict
+            .as_ref()
+            .and_then(|map| map.get($field).ok().map(|v| v.as_str()?.parse()))
+            .transpose()
+            .context(format!("invalid attribute `{}`", $field))?
+    };
+}
+
+macro_rules! extract_parse {
     ($dict:expr, $field:expr) => {
         $dict
             .as_ref()
@@ -103,12 +166,23 @@ macro_rules! extract_str {
 pub struct FieldAttr {
     pub flatten: bool,
     pub rename: Option<String>,
+    pub aliases: Vec<String>,
     pub default: Option<SyncObject>,
     pub default_factory: Option<SyncObject>,
+    pub init: bool,
+    pub kw_only: bool,
     pub skip: bool,
     pub skip_serializing: bool,
     pub skip_deserializing: bool,
+    pub skip_serializing_if: Option<SkipIf>,
     pub default_construct: bool,
+    pub tag: Option<String>,
+    pub content: Option<String>,
+    pub externally_tagged: bool,
+    pub untagged: bool,
+    pub constraints: Constraints,
+    pub serialize_with: Option<SyncObject>,
+    pub deserialize_with: Option<SyncObject>,
 }
 
 impl FieldAttr {
@@ -116,18 +190,198 @@ impl FieldAttr {
         attr: Option<Object>,
         default: Option<Object>,
         default_factory: Option<Object>,
+        init: bool,
+        kw_only: bool,
     ) -> Result<Self> {
         Ok(Self::new(
             field_extract_bool!(attr, "perde_flatten"),
             field_extract_str!(attr, "perde_rename"),
+            parse_aliases(&attr).context("invalid attribute `perde_alias`")?,
             default.map(|o| o.into()),
             default_factory.map(|o| o.into()),
+            init,
+            kw_only,
             field_extract_bool!(attr, "perde_skip"),
             field_extract_bool!(attr, "perde_skip_serializing"),
             field_extract_bool!(attr, "perde_skip_deserializing"),
+            field_extract_parse!(attr, "perde_skip_serializing_if"),
             field_extract_bool!(attr, "perde_default"),
+            field_extract_str!(attr, "perde_tag"),
+            field_extract_str!(attr, "perde_content"),
+            field_extract_bool!(attr, "perde_externally_tagged"),
+            field_extract_bool!(attr, "perde_untagged"),
+            Constraints::parse(&attr)?,
+            field_extract_callable!(attr, "perde_serialize_with"),
+            field_extract_callable!(attr, "perde_deserialize_with"),
         ))
     }
+
+    pub fn tagging(&self) -> Result<Option<Tagging>> {
+        Tagging::parse(
+            self.tag.as_ref(),
+            self.content.as_ref(),
+            self.externally_tagged,
+            self.untagged,
+        )
+        .context("invalid tagging attributes")
+    }
+}
+
+/// The constraints on the value of a field, checked when deserializing.
+#[derive(Clone, Debug, Default, new, PartialEq, Eq)]
+pub struct Constraints {
+    pub ge: Option<SyncObject>,
+    pub le: Option<SyncObject>,
+    pub min_len: Option<usize>,
+    pub max_len: Option<usize>,
+    pub pattern: Option<Pattern>,
+}
+
+impl Constraints {
+    pub fn parse(attr: &Option<Object>) -> Result<Self> {
+        let get = |field: &str| attr.as_ref().and_then(|map| map.get(field).ok());
+        let len = |field: &str| {
+            get(field)
+                .map(|v| match v.as_i64()? {
+                    n if n < 0 => bail_value_err!("the length must not be negative: got {}", n),
+                    n => Ok(n as usize),
+                })
+                .transpose()
+                .context(format!("invalid attribute `{}`", field))
+        };
+        let pattern = get("perde_pattern")
+            .map(|v| Pattern::compile(v.as_str()?))
+            .transpose()
+            .context("invalid attribute `perde_pattern`")?;
+
+        Ok(Self::new(
+            get("perde_ge").map(|v| v.into()),
+            get("perde_le").map(|v| v.into()),
+            len("perde_min_len")?,
+            len("perde_max_len")?,
+            pattern,
+        ))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ge.is_none()
+            && self.le.is_none()
+            && self.min_len.is_none()
+            && self.max_len.is_none()
+            && self.pattern.is_none()
+    }
+
+    /// Checks if the constraints can be applied to the type of the field.
+    pub fn check(&self, schema: &Schema) -> Result<()> {
+        let ordered = |s: &Schema| {
+            matches!(
+                s,
+                Schema::Int
+                    | Schema::Float
+                    | Schema::Decimal
+                    | Schema::DateTime
+                    | Schema::Date
+                    | Schema::Time
+            )
+        };
+        let string = |s: &Schema| matches!(s, Schema::Str);
+
+        let check = |name: &str, set: bool, f: &dyn Fn(&Schema) -> bool| {
+            if set && !schema.all_variants(f) {
+                bail_value_err!("cannot use `{}` with `{}`", name, schema.name());
+            }
+            Ok(())
+        };
+        check("perde_ge", self.ge.is_some(), &ordered)?;
+        check("perde_le", self.le.is_some(), &ordered)?;
+        check("perde_min_len", self.min_len.is_some(), &sized)?;
+        check("perde_max_len", self.max_len.is_some(), &sized)?;
+        check("perde_pattern", self.pattern.is_some(), &string)?;
+
+        for (name, bound) in &[("perde_ge", &self.ge), ("perde_le", &self.le)] {
+            if let Some(bound) = bound {
+                if !schema.all_variants(&|s| comparable(s, bound)) {
+                    bail_value_err!(
+                        "`{}` of {:?} cannot be compared with `{}`",
+                        name,
+                        bound.repr()?,
+                        schema.name()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if the bound given by `perde_ge` or `perde_le` can be compared
+/// with the values of the type.
+fn comparable(s: &Schema, bound: &ObjectRef) -> bool {
+    let ty = match bound.get_type() {
+        Ok(ty) => ty,
+        Err(_) => return false,
+    };
+    match s {
+        Schema::Int | Schema::Float | Schema::Decimal => {
+            ty.is_int() || ty.is_float() || ty.is_decimal()
+        }
+        Schema::DateTime => ty.is_datetime(),
+        Schema::Date => ty.is_date(),
+        Schema::Time => ty.is_time(),
+        _ => false,
+    }
+}
+
+/// Parses `perde_alias`, which is either a string or a list of strings.
+fn parse_aliases(attr: &Option<Object>) -> Result<Vec<String>> {
+    let aliases = match attr.as_ref().and_then(|map| map.get("perde_alias").ok()) {
+        Some(aliases) => aliases,
+        None => return Ok(vec![]),
+    };
+    if aliases.is_str_object() {
+        return Ok(vec![aliases.as_str()?.to_string()]);
+    }
+    aliases
+        .get_iter()?
+        .map(|a| Ok(a?.as_str()?.to_string()))
+        .collect()
+}
+
+/// Returns true if the values of the type have the length.
+fn sized(s: &Schema) -> bool {
+    matches!(
+        s,
+        Schema::Str
+            | Schema::Bytes
+            | Schema::ByteArray
+            | Schema::Dict(_)
+            | Schema::List(_)
+            | Schema::Set(_)
+            | Schema::FrozenSet(_)
+            | Schema::Tuple(_)
+    )
+}
+
+/// The regular expression given by `perde_pattern`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub pattern: String,
+    /// `re.Pattern.search` of the compiled pattern.
+    pub search: SyncObject,
+}
+
+impl Pattern {
+    fn compile(pattern: &str) -> Result<Self> {
+        // `re.error` is raised as the schema error.
+        let compiled = import()?
+            .re_compile
+            .call1(Object::new_str(pattern)?)
+            .map_err(|e| err!("{}", e.describe_exception()))?;
+        Ok(Self {
+            pattern: pattern.to_string(),
+            search: compiled.get_attr(&AttrStr::new("search"))?.into(),
+        })
+    }
 }
 
 #[derive(Clone, Debug, Default, new, PartialEq, Eq)]
@@ -151,6 +405,7 @@ impl VariantAttr {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 #[derive(Clone, Debug, Default, new, PartialEq, Eq)]
 pub struct ClassAttr {
     pub rename_all: Option<StrCase>,
@@ -159,19 +414,43 @@ pub struct ClassAttr {
     pub rename: Option<String>,
     pub deny_unknown_fields: bool,
     pub default: bool,
+    pub tag: Option<String>,
+    pub content: Option<String>,
+    pub externally_tagged: bool,
+    pub untagged: bool,
+    pub as_array: bool,
+    pub skip_serializing_if: Option<SkipIf>,
+    pub transparent: bool,
 }
 
 impl ClassAttr {
     pub fn parse(attr: &Option<HashMap<&str, &ObjectRef>>) -> Result<Self> {
         Ok(Self::new(
-            extract_stringcase!(attr, "rename_all"),
-            extract_stringcase!(attr, "rename_all_serialize"),
-            extract_stringcase!(attr, "rename_all_deserialize"),
+            extract_parse!(attr, "rename_all"),
+            extract_parse!(attr, "rename_all_serialize"),
+            extract_parse!(attr, "rename_all_deserialize"),
             extract_str!(attr, "rename"),
             extract_bool!(attr, "deny_unknown_fields"),
             extract_bool!(attr, "default"),
+            extract_str!(attr, "tag"),
+            extract_str!(attr, "content"),
+            extract_bool!(attr, "externally_tagged"),
+            extract_bool!(attr, "untagged"),
+            extract_bool!(attr, "as_array"),
+            extract_parse!(attr, "skip_serializing_if"),
+            extract_bool!(attr, "transparent"),
         ))
     }
+
+    pub fn tagging(&self) -> Result<Option<Tagging>> {
+        Tagging::parse(
+            self.tag.as_ref(),
+            self.content.as_ref(),
+            self.externally_tagged,
+            self.untagged,
+        )
+        .context("invalid tagging attributes")
+    }
 }
 
 #[derive(Clone, Debug, Default, new, PartialEq, Eq)]
@@ -186,9 +465,9 @@ pub struct EnumAttr {
 impl EnumAttr {
     pub fn parse(attr: &Option<HashMap<&str, &ObjectRef>>) -> Result<Self> {
         Ok(Self::new(
-            extract_stringcase!(attr, "rename_all"),
-            extract_stringcase!(attr, "rename_all_serialize"),
-            extract_stringcase!(attr, "rename_all_deserialize"),
+            extract_parse!(attr, "rename_all"),
+            extract_parse!(attr, "rename_all_serialize"),
+            extract_parse!(attr, "rename_all_deserialize"),
             extract_str!(attr, "rename"),
             extract_bool!(attr, "as_value"),
         ))
@@ -283,36 +562,114 @@ pub struct VariantSchema {
     pub value: SyncObject,
 }
 
+#[allow(clippy::too_many_arguments)]
 #[derive(Debug, Clone, new, PartialEq, Eq)]
 pub struct Class {
     pub ty: SyncObject,
     pub name: String,
+    /// The type arguments of a generic class, e.g. `User` of `Page[User]`.
+    pub args: Vec<SyncObject>,
     pub attr: ClassAttr,
     pub fields: IndexMap<String, FieldSchema>,
     pub flatten_fields: IndexMap<String, FieldSchema>,
+    /// The aliases given by `perde_alias`, mapped to the names of the fields.
+    pub aliases: HashMap<String, String>,
     pub flatten_dict: Option<Dict>,
-    pub ser_field_len: usize,
+    /// The number of the serialized fields, or `None` if it varies by the value.
+    pub ser_field_len: Option<usize>,
 }
 
 impl Class {
     pub fn name(&self) -> &str {
         self.ty.name()
     }
+
+    /// The name of the class used as the tag of unions.
+    pub fn tag_name(&self) -> &str {
+        self.attr.rename.as_deref().unwrap_or(&self.name)
+    }
+
+    /// The tag given by the class attribute `tag` without `content`, which is
+    /// written along with the fields even if the class is not in a union.
+    pub fn internal_tag(&self) -> Option<&str> {
+        match (&self.attr.tag, &self.attr.content) {
+            (Some(tag), None) => Some(tag),
+            _ => None,
+        }
+    }
+
+    /// Returns the only field which is not skipped, if the class is `transparent`.
+    pub fn transparent_field(&self) -> Option<(&String, &FieldSchema)> {
+        if self.attr.transparent {
+            self.fields.iter().find(|(_, f)| !f.attr.skip)
+        } else {
+            None
+        }
+    }
 }
 
 #[derive(Debug, Clone, new, PartialEq, Eq)]
 pub struct FieldSchema {
     pub name: AttrStr,
+    /// The name of the argument of the constructor, e.g. `p` for `_p` of attrs.
+    pub arg_name: AttrStr,
     pub rename: String,
     pub pos: usize,
     pub attr: FieldAttr,
     pub schema: Schema,
+    /// The metadata given by `Annotated[T, ...]`.
+    pub annotated: Vec<SyncObject>,
 }
 
 #[derive(Debug, Clone, new, PartialEq, Eq)]
 pub struct Union {
     pub variants: Vec<Schema>,
     pub optional: bool,
+    pub tagging: Tagging,
+}
+
+/// How the variant of a union is represented.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tagging {
+    /// The variant is picked by the shape of the input, e.g. a map or a string.
+    Shape,
+    /// Each variant is tried in order against the buffered input.
+    Untagged,
+    /// `{"variant": {...}}`
+    External,
+    /// `{"tag": "variant", ...}`
+    Internal { tag: String },
+    /// `{"tag": "variant", "content": {...}}`
+    Adjacent { tag: String, content: String },
+}
+
+impl Tagging {
+    fn parse(
+        tag: Option<&String>,
+        content: Option<&String>,
+        externally_tagged: bool,
+        untagged: bool,
+    ) -> Result<Option<Self>> {
+        match (tag, content, externally_tagged, untagged) {
+            (None, None, false, false) => Ok(None),
+            (None, None, true, false) => Ok(Some(Tagging::External)),
+            (None, None, false, true) => Ok(Some(Tagging::Untagged)),
+            (Some(tag), None, false, false) => Ok(Some(Tagging::Internal { tag: tag.clone() })),
+            (Some(tag), Some(content), false, false) => Ok(Some(Tagging::Adjacent {
+                tag: tag.clone(),
+                content: content.clone(),
+            })),
+            (None, Some(_), _, _) => bail_value_err!("`content` requires `tag`"),
+            _ => bail_value_err!(
+                "only one of `tag`, `externally_tagged` and `untagged` can be specified"
+            ),
+        }
+    }
+
+    /// Returns true if the variants are identified by their names.
+    pub fn is_tagged(&self) -> bool {
+        !matches!(self, Tagging::Shape | Tagging::Untagged)
+    }
 }
 
 impl Union {
@@ -321,9 +678,107 @@ impl Union {
     }
 }
 
+#[derive(Debug, Clone, new, PartialEq, Eq)]
+pub struct Literal {
+    pub values: Vec<LiteralValue>,
+}
+
+impl Literal {
+    pub fn name(&self) -> &str {
+        "Literal"
+    }
+
+    /// Returns the index of the literal value equal to the object.
+    pub fn position(&self, object: &ObjectRef) -> Option<usize> {
+        let ty = object.get_type().ok()?;
+        // `True == 1` in Python, so compare the types as well.
+        self.values.iter().position(|v| {
+            matches!(v.value.get_type(), Ok(t) if t.is(ty.as_ptr()))
+                && v.value.equals(object).unwrap_or(false)
+        })
+    }
+
+    /// Formats the allowed values for the error messages.
+    pub fn values(&self) -> String {
+        let values: Vec<_> = self
+            .values
+            .iter()
+            .map(|v| format!("{:?}", v.data.repr().unwrap_or_else(|_| v.data.owned())))
+            .collect();
+        format!("[{}]", values.join(", "))
+    }
+}
+
+/// A value allowed by `Literal`, and how it appears in the serialized data,
+/// e.g. the name of the enum member.
+#[derive(Debug, Clone, new, PartialEq, Eq)]
+pub struct LiteralValue {
+    pub value: SyncObject,
+    pub data: SyncObject,
+}
+
+/// Reference to the class which appears in its own fields, directly or
+/// indirectly. The schema is looked up when used, because it's not ready
+/// until the resolution of the class completes.
+#[derive(Debug, Clone, new, PartialEq, Eq)]
+pub struct ClassRef {
+    pub ty: SyncObject,
+}
+
+impl ClassRef {
+    pub fn name(&self) -> &str {
+        // Not resolved here, as the schema may be incomplete while the class resolves.
+        self.ty.name()
+    }
+
+    pub fn resolve(&self) -> Result<&Schema> {
+        match self.ty.resolve(None)? {
+            Cow::Borrowed(s) => Ok(s),
+            Cow::Owned(_) => bail!("the schema of `{}` is not cached", self.ty.name()),
+        }
+    }
+}
+
+/// The type converted to and from another type by the functions registered with
+/// `perde.register`.
+#[derive(Debug, Clone, new, PartialEq, Eq)]
+pub struct Converter {
+    pub ty: SyncObject,
+    pub to_primitive: SyncObject,
+    pub from_primitive: SyncObject,
+    pub schema: Box<Schema>,
+}
+
+impl Converter {
+    pub fn name(&self) -> &str {
+        self.ty.name()
+    }
+}
+
+#[derive(Debug, Clone, new, PartialEq, Eq)]
+pub struct TypedDict {
+    pub ty: SyncObject,
+    pub name: String,
+    pub fields: IndexMap<String, TypedDictField>,
+}
+
+impl TypedDict {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Debug, Clone, new, PartialEq, Eq)]
+pub struct TypedDictField {
+    pub schema: Schema,
+    pub required: bool,
+}
+
 #[derive(Debug, Clone, new, PartialEq, Eq)]
 pub struct Any;
 
+// The schemas are resolved once and cached, so the size of `Class` doesn't matter much.
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, new, PartialEq, Eq)]
 pub enum Schema {
     Bool,
@@ -343,9 +798,13 @@ pub enum Schema {
     FrozenSet(FrozenSet),
     Tuple(Tuple),
     Class(Class),
+    TypedDict(TypedDict),
     Enum(Enum),
     Union(Union),
+    Literal(Literal),
+    Converter(Converter),
     Any(Any),
+    Ref(ClassRef),
 }
 
 impl Schema {
@@ -368,9 +827,21 @@ impl Schema {
             Self::FrozenSet(s) => s.name(),
             Self::Tuple(t) => t.name(),
             Self::Class(c) => c.name(),
+            Self::TypedDict(d) => d.name(),
             Self::Enum(e) => e.name(),
             Self::Union(u) => u.name(),
+            Self::Literal(l) => l.name(),
+            Self::Converter(c) => c.name(),
             Self::Any(_) => "any",
+            Self::Ref(r) => r.name(),
+        }
+    }
+
+    /// Returns the schema which the reference points to, or the schema itself.
+    pub fn resolved(&self) -> Result<&Schema> {
+        match self {
+            Self::Ref(r) => r.resolve(),
+            s => Ok(s),
         }
     }
 
@@ -382,6 +853,14 @@ impl Schema {
         }
     }
 
+    /// Returns true if all the variants of the union, or the schema itself satisfy `f`.
+    pub fn all_variants(&self, f: &dyn Fn(&Schema) -> bool) -> bool {
+        match self {
+            Self::Union(u) => u.variants.iter().all(|v| v.all_variants(f)),
+            s => f(s),
+        }
+    }
+
     pub fn borrowed(&self) -> Cow<'_, Self> {
         Cow::Borrowed(self)
     }
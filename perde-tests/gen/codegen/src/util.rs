@@ -45,6 +45,7 @@ fn add_value_except<T: Serialize + Debug>(name: &str, data: T, excepts: &[&str])
     insert!("yaml", serde_yaml::to_vec);
     insert!("msgpack", rmp_serde::to_vec_named);
     insert!("toml", toml::to_vec);
+    insert!("cbor", serde_cbor::to_vec);
 }
 
 macro_rules! add {
@@ -47,6 +47,7 @@ fn add_value_except<T: Serialize + Debug>(name: &str, data: T, excepts: &[&str])
     insert!("yaml", serde_yaml::to_vec);
     insert!("msgpack", rmp_serde::to_vec_named);
     insert!("toml", toml::to_vec);
+    insert!("cbor", serde_cbor::to_vec);
 }
 
 macro_rules! add {
@@ -84,6 +85,229 @@ struct Opt {
 }
 
 fn main() {
+    #[derive(Serialize, Debug, new)]
+    struct FutureAnnotationsChild {
+        x: i64,
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct FutureAnnotations {
+        a: i64,
+        b: String,
+        c: Vec<FutureAnnotationsChild>,
+        d: Option<FutureAnnotationsChild>,
+    }
+
+    add!(FutureAnnotations {
+     3,
+     "xxx".into(),
+     vec![FutureAnnotationsChild::new(1), FutureAnnotationsChild::new(2)],
+     Some(FutureAnnotationsChild::new(3))
+    });
+
+    #[derive(Serialize, Debug, new)]
+    struct RecursiveTree {
+        value: i64,
+        children: Vec<RecursiveTree>,
+    }
+
+    add!(RecursiveTree {
+     1,
+     vec![
+       RecursiveTree::new(2, vec![]),
+       RecursiveTree::new(3, vec![RecursiveTree::new(4, vec![])]),
+     ]
+    });
+
+    #[derive(Serialize, Debug, new)]
+    struct RecursiveList {
+        value: i64,
+        next: Option<Box<RecursiveList>>,
+    }
+
+    add!(RecursiveList {
+     1,
+     Some(Box::new(RecursiveList::new(2, Some(Box::new(RecursiveList::new(3, None))))))
+    }
+    except "toml");
+
+    #[derive(Serialize, Debug, new)]
+    struct MutualDir {
+        name: String,
+        entries: Vec<MutualEntry>,
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct MutualEntry {
+        dir: Option<MutualDir>,
+        size: i64,
+    }
+
+    add!(MutualDir {
+     "root".into(),
+     vec![
+       MutualEntry::new(None, 3),
+       MutualEntry::new(Some(MutualDir::new("sub".into(), vec![])), 0),
+     ]
+    }
+    except "toml");
+
+    #[derive(Serialize, Debug, new)]
+    #[serde(rename_all = "camelCase")]
+    struct RecursiveAttr {
+        node_name: String,
+        child_nodes: HashMap<String, RecursiveAttr>,
+    }
+
+    add!(RecursiveAttr {
+     "a".into(),
+     vec![("b".into(), RecursiveAttr::new("c".into(), HashMap::new()))]
+       .into_iter()
+       .collect()
+    });
+
+    #[derive(Serialize, Debug, new)]
+    struct GenericUser {
+        name: String,
+        age: i64,
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct GenericPage {
+        total: i64,
+        items: Vec<GenericUser>,
+    }
+
+    add!(GenericPage {
+     2,
+     vec![GenericUser::new("xxx".into(), 3), GenericUser::new("yyy".into(), 4)]
+    });
+
+    #[derive(Serialize, Debug, new)]
+    #[serde(rename_all = "camelCase")]
+    struct GenericPair {
+        first_value: i64,
+        second_values: HashMap<String, Vec<String>>,
+    }
+
+    add!(GenericPair {
+     1,
+     vec![("a".into(), vec!["b".into(), "c".into()])].into_iter().collect()
+    });
+
+    #[derive(Serialize, Debug, new)]
+    struct GenericTree {
+        value: String,
+        children: Vec<GenericTree>,
+    }
+
+    add!(GenericTree {
+     "a".into(),
+     vec![
+       GenericTree::new("b".into(), vec![]),
+       GenericTree::new("c".into(), vec![GenericTree::new("d".into(), vec![])]),
+     ]
+    });
+
+    #[derive(Serialize, Debug, new)]
+    struct GenericNested {
+        next: Option<i64>,
+        page: GenericPage,
+    }
+
+    add!(GenericNested {
+     Some(2),
+     GenericPage::new(1, vec![GenericUser::new("xxx".into(), 3)])
+    });
+
+    #[derive(Serialize, Debug, new)]
+    struct NewTypeField {
+        id: i64,
+        friends: Vec<i64>,
+        parent: Option<i64>,
+    }
+
+    add!(NewTypeField {3, vec![4, 5], Some(6)});
+
+    #[derive(Serialize, Debug, new)]
+    struct AnnotatedField {
+        id: i64,
+        #[serde(rename = "userName")]
+        name: String,
+        #[serde(rename = "labels")]
+        tags: Vec<String>,
+    }
+
+    add!(AnnotatedField {3, "xxx".into(), vec!["a".into(), "b".into()]});
+
+    #[derive(Serialize, Debug, new)]
+    struct Constrained {
+        age: i64,
+        ratio: f64,
+        name: String,
+        tags: Vec<String>,
+        labels: HashMap<String, String>,
+    }
+
+    add!(Constrained {
+     30,
+     0.5,
+     "xxx".into(),
+     vec!["a".into()],
+     vec![("k".into(), "v".into())].into_iter().collect()
+    });
+
+    #[derive(Serialize, Debug, new)]
+    struct Registered {
+        path: String,
+        ip: String,
+        price: Vec<String>,
+        ratio: f64,
+        paths: Vec<String>,
+    }
+
+    add!(Registered {
+     "/tmp/x".into(),
+     "10.0.0.1".into(),
+     vec!["1.5".into(), "USD".into()],
+     0.5,
+     vec!["a".into(), "b/c".into()]
+    });
+
+    #[derive(Serialize, Debug, new)]
+    struct AttrsClass {
+        id: i64,
+        #[serde(rename = "userName")]
+        name: String,
+        tags: Vec<String>,
+    }
+
+    add!(AttrsClass {3, "xxx".into(), vec!["a".into(), "b".into()]});
+
+    #[derive(Serialize, Debug, new)]
+    struct NamedTupleMap {
+        x: i64,
+        label: String,
+    }
+
+    add!(NamedTupleMap {3, "p".into()});
+
+    #[derive(Serialize, Debug, new)]
+    struct NamedTupleArray {
+        point: (i64, String),
+    }
+
+    add!(NamedTupleArray {(3, "p".into())});
+
+    #[derive(Serialize, Debug, new)]
+    struct TypedDictUser {
+        id: i64,
+        name: String,
+        tags: Vec<String>,
+    }
+
+    add!(TypedDictUser {3, "xxx".into(), vec!["a".into()]});
+
     add_value("BenchNumber", 1311);
     add_value("BenchString", "aiueo");
 
@@ -97,6 +321,48 @@ fn main() {
 
     add_value("BenchList", vec![1i64, 2, -3, 4, 5, -8]);
 
+    #[derive(Serialize, Debug, new)]
+    struct LiteralPrimitive {
+        a: String,
+        b: i64,
+        c: bool,
+        d: String,
+    }
+
+    add!(LiteralPrimitive {"yyy".into(), 2, true, "Blue".into()});
+
+    #[derive(Serialize, Debug, new)]
+    struct LiteralCircle {
+        kind: String,
+        radius: i64,
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct LiteralRect {
+        kind: String,
+        width: i64,
+        height: i64,
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(untagged)]
+    enum LiteralShape {
+        Circle(LiteralCircle),
+        Rect(LiteralRect),
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct LiteralUnion {
+        shapes: Vec<LiteralShape>,
+    }
+
+    add!(LiteralUnion {
+     vec![
+       LiteralShape::Rect(LiteralRect::new("rect".into(), 1, 2)),
+       LiteralShape::Circle(LiteralCircle::new("circle".into(), 3)),
+     ]
+    });
+
     #[derive(Serialize, Debug, new)]
     struct Plain {
         a: String,
@@ -238,7 +504,7 @@ fn main() {
      {"xxx".into(),
       FlattenChild::new("ppp".into(), "qqq".into()),
       1111}
-     except "msgpack");
+     except "msgpack", "cbor");
 
     #[derive(Serialize, Debug, new)]
     struct DictFlatten {
@@ -279,8 +545,137 @@ fn main() {
     add!(DictFlatten2 {
      "hey".into(), -103223,
      "q1".into(), "q2".into(), "q3".into()
+    } except "cbor");
+
+    #[derive(Serialize, Debug)]
+    #[serde(tag = "type")]
+    enum InternalTagAnimal {
+        Cat { name: String, lives: i64 },
+        Dog { name: String, good: bool },
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct InternalTag {
+        a: InternalTagAnimal,
+        b: InternalTagAnimal,
+    }
+
+    add!(InternalTag {
+     InternalTagAnimal::Cat { name: "tama".into(), lives: 9 },
+     InternalTagAnimal::Dog { name: "pochi".into(), good: true }
     });
 
+    #[derive(Serialize, Debug)]
+    #[serde(tag = "t", content = "c")]
+    enum AdjacentTagAnimal {
+        Cat {
+            name: String,
+            lives: i64,
+        },
+        #[serde(rename = "dog")]
+        Dog {
+            name: String,
+            good: bool,
+        },
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct AdjacentTag {
+        a: AdjacentTagAnimal,
+        b: AdjacentTagAnimal,
+    }
+
+    add!(AdjacentTag {
+     AdjacentTagAnimal::Cat { name: "tama".into(), lives: 9 },
+     AdjacentTagAnimal::Dog { name: "pochi".into(), good: true }
+    }
+    except "msgpack");
+
+    #[derive(Serialize, Debug)]
+    enum ExternalTagAnimal {
+        Cat { name: String, lives: i64 },
+        Dog { name: String, good: bool },
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct ExternalTag {
+        a: ExternalTagAnimal,
+        b: ExternalTagAnimal,
+    }
+
+    add!(ExternalTag {
+     ExternalTagAnimal::Cat { name: "tama".into(), lives: 9 },
+     ExternalTagAnimal::Dog { name: "pochi".into(), good: true }
+    }
+    except "toml", "msgpack");
+
+    #[derive(Serialize, Debug, new)]
+    struct UntaggedPoint2D {
+        x: i64,
+        y: i64,
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct UntaggedPoint3D {
+        x: i64,
+        y: i64,
+        z: i64,
+    }
+
+    #[derive(Serialize, Debug)]
+    #[serde(untagged)]
+    enum UntaggedPoint {
+        P3(UntaggedPoint3D),
+        P2(UntaggedPoint2D),
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct Untagged {
+        a: UntaggedPoint,
+        b: UntaggedPoint,
+    }
+
+    add!(Untagged {
+     UntaggedPoint::P3(UntaggedPoint3D::new(1, 2, 3)),
+     UntaggedPoint::P2(UntaggedPoint2D::new(4, 5))
+    });
+
+    #[derive(Serialize, Debug, new)]
+    struct WithHooks {
+        a: i64,
+        tags: String,
+        code: String,
+    }
+
+    add!(WithHooks {3, "x,y,z".into(), "ff".into()});
+
+    #[derive(Serialize, Debug, new)]
+    struct SkipIf {
+        a: i64,
+        b: Option<i64>,
+        c: Vec<i64>,
+        d: String,
+    }
+
+    add!(SkipIf {3, Some(4), vec![5], "y".into()});
+
+    #[derive(Serialize, Debug, new)]
+    struct Alias {
+        #[serde(rename = "userName")]
+        user_name: String,
+        id: i64,
+    }
+
+    add!(Alias {"xxx".into(), 3});
+
+    #[derive(Serialize, Debug, new)]
+    struct Transparent {
+        email: String,
+        ids: Vec<String>,
+    }
+
+    add!(Transparent {"a@example.com".into(), vec!["x".into(), "y".into()]});
+
     #[derive(Serialize, Debug, new)]
     struct TypeMismatch {
         a: String,
@@ -305,6 +700,26 @@ fn main() {
 
     add!(TooManyMember { "hage".into(), "faa".into(), 33 });
 
+    #[derive(Serialize, Debug, new)]
+    struct ErrorPathItem {
+        price: String,
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct ErrorPathOrder {
+        id: String,
+        items: Vec<ErrorPathItem>,
+    }
+
+    #[derive(Serialize, Debug, new)]
+    struct ErrorPath {
+        order: ErrorPathOrder,
+    }
+
+    add!(ErrorPath {
+      ErrorPathOrder::new("x".into(), vec![ErrorPathItem::new("a".into()), ErrorPathItem::new("b".into())])
+    });
+
     #[derive(Serialize, Debug, new)]
     struct SkipEnumError {
         x: i64,
@@ -367,6 +782,25 @@ fn main() {
     add!(Skipped {"ssssss".into(), 3, 1.1, "a".into(), "b".into()});
     add!(SkipDefault {"ssssss".into(), 0, 1.1, "a".into(), "b".into()});
 
+    #[derive(Serialize, Debug, new)]
+    struct InitFalse {
+        x: String,
+        y: i64,
+        z: i64,
+    }
+
+    add!(InitFalse {"xxx".into(), 3, 6});
+
+    #[derive(Serialize, Debug, new)]
+    struct KwOnly {
+        a: i64,
+        b: String,
+        c: i64,
+        d: String,
+    }
+
+    add!(KwOnly {3, "xxx".into(), 4, "yyy".into()});
+
     add_value("EnumX", "X");
     add_value("EnumY", "Y");
     add_value("EnumZ", "Z");
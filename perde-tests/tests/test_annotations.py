@@ -0,0 +1,72 @@
+from __future__ import annotations
+from dataclasses import dataclass
+from typing import List, Optional
+import pytest
+from util import FORMATS
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct FutureAnnotationsChild {
+  x: i64,
+}
+
+#[derive(Serialize, Debug, new)]
+struct FutureAnnotations {
+  a: i64,
+  b: String,
+  c: Vec<FutureAnnotationsChild>,
+  d: Option<FutureAnnotationsChild>,
+}
+
+add!(FutureAnnotations {
+     3,
+     "xxx".into(),
+     vec![FutureAnnotationsChild::new(1), FutureAnnotationsChild::new(2)],
+     Some(FutureAnnotationsChild::new(3))
+    });
+"""
+
+
+@dataclass
+class FutureAnnotations:
+    a: int
+    b: str
+    c: List[FutureAnnotationsChild]
+    d: Optional[FutureAnnotationsChild]
+
+
+@dataclass
+class FutureAnnotationsChild:
+    x: int
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_future_annotations(m):
+    m.repack_type(FutureAnnotations)
+
+
+@dataclass
+class ForwardRef:
+    a: "List[ForwardRefChild]"
+
+
+@dataclass
+class ForwardRefChild:
+    x: int
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_forward_ref(m):
+    m.repack_as(ForwardRef, ForwardRef([ForwardRefChild(1), ForwardRefChild(2)]))
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_unresolved_annotations(m):
+    @dataclass
+    class Unresolved:
+        a: int
+        b: NotDefined  # noqa: F821
+
+    with pytest.raises(NameError) as e:
+        m.loads_as(Unresolved, m.dumps({"a": 1, "b": 2}))
+    assert e.value.args[0] == "name 'NotDefined' is not defined"
@@ -0,0 +1,233 @@
+from dataclasses import dataclass, field
+import datetime
+from decimal import Decimal
+import sys
+from typing import Dict, List, Optional
+import perde
+import pytest
+from util import FORMATS, FORMATS_EXCEPT
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct Constrained {
+  age: i64,
+  ratio: f64,
+  name: String,
+  tags: Vec<String>,
+  labels: HashMap<String, String>,
+}
+
+add!(Constrained {
+     30,
+     0.5,
+     "xxx".into(),
+     vec!["a".into()],
+     vec![("k".into(), "v".into())].into_iter().collect()
+    });
+"""
+
+
+@dataclass
+class Constrained:
+    age: int = field(metadata={"perde_ge": 0, "perde_le": 150})
+    ratio: float = field(metadata={"perde_ge": 0.0, "perde_le": 1.0})
+    name: str = field(
+        metadata={"perde_min_len": 1, "perde_max_len": 8, "perde_pattern": "^[a-z]+$"}
+    )
+    tags: List[str] = field(metadata={"perde_min_len": 1})
+    labels: Dict[str, str] = field(metadata={"perde_max_len": 2})
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_constraints(m):
+    m.repack_type(Constrained)
+
+
+def constrained(**kwargs):
+    v = {"age": 30, "ratio": 0.5, "name": "xxx", "tags": ["a"], "labels": {}}
+    v.update(kwargs)
+    return v
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_constraints_violated(m):
+    def check(v, path, msg):
+        with pytest.raises(m.package.ValidationError) as e:
+            m.loads_as(Constrained, m.dumps(v))
+        assert isinstance(e.value, perde.ValidationError)
+        assert isinstance(e.value, perde.DecodeError)
+        assert e.value.path == path
+        assert e.value.args[0].startswith(msg)
+
+    check(
+        constrained(age=-1),
+        "age",
+        "the value must be greater than or equal to 0: got -1",
+    )
+    check(
+        constrained(age=151),
+        "age",
+        "the value must be less than or equal to 150: got 151",
+    )
+    check(
+        constrained(ratio=1.5),
+        "ratio",
+        "the value must be less than or equal to 1.0: got 1.5",
+    )
+    check(constrained(name=""), "name", "the length must be at least 1: got 0")
+    check(
+        constrained(name="abcdefghi"), "name", "the length must be at most 8: got 9"
+    )
+    check(
+        constrained(name="Xyz"),
+        "name",
+        "the value must match the pattern `^[a-z]+$`: got 'Xyz'",
+    )
+    check(constrained(tags=[]), "tags", "the length must be at least 1: got 0")
+    check(
+        constrained(labels={"a": "1", "b": "2", "c": "3"}),
+        "labels",
+        "the length must be at most 2: got 3",
+    )
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_constraints_collect_errors(m):
+    with pytest.raises(m.package.DecodeError) as e:
+        m.loads_as(
+            Constrained, m.dumps(constrained(age=-1, tags=[])), collect_errors=True
+        )
+    assert e.value.errors == [
+        ("age", "the value must be greater than or equal to 0: got -1", "validation"),
+        ("tags", "the length must be at least 1: got 0", "validation"),
+    ]
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_constraints_optional(m):
+    @dataclass
+    class ConstrainedOptional:
+        a: Optional[int] = field(default=None, metadata={"perde_ge": 1})
+
+    m.repack_as(ConstrainedOptional, ConstrainedOptional(None))
+    m.repack_as(ConstrainedOptional, ConstrainedOptional(1))
+    with pytest.raises(m.package.ValidationError):
+        m.loads_as(ConstrainedOptional, m.dumps({"a": 0}))
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_constraints_comparable(m):
+    @dataclass
+    class ConstrainedComparable:
+        a: Decimal = field(metadata={"perde_ge": Decimal("0.1")})
+        b: datetime.date = field(metadata={"perde_le": datetime.date(2000, 1, 1)})
+
+    m.repack_as(
+        ConstrainedComparable,
+        ConstrainedComparable(Decimal("0.1"), datetime.date(1999, 12, 31)),
+    )
+    with pytest.raises(m.package.ValidationError):
+        m.loads_as(
+            ConstrainedComparable,
+            m.dumps(ConstrainedComparable(Decimal("0"), datetime.date(1999, 1, 1))),
+        )
+    with pytest.raises(m.package.ValidationError):
+        m.loads_as(
+            ConstrainedComparable,
+            m.dumps(ConstrainedComparable(Decimal("1"), datetime.date(2000, 1, 2))),
+        )
+
+
+@pytest.mark.skipif(sys.version_info < (3, 9), reason="requires python 3.9")
+@pytest.mark.parametrize("m", FORMATS)
+def test_constraints_annotated(m):
+    from typing import Annotated
+
+    @dataclass
+    class ConstrainedAnnotated:
+        a: Annotated[int, {"perde_ge": 0}]
+        b: Annotated[List[int], {"perde_min_len": 1, "perde_max_len": 2}]
+
+    m.repack_as(ConstrainedAnnotated, ConstrainedAnnotated(0, [1, 2]))
+    with pytest.raises(m.package.ValidationError):
+        m.loads_as(ConstrainedAnnotated, m.dumps({"a": 0, "b": []}))
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_constraints_invalid(m):
+    @dataclass
+    class ConstrainedList:
+        a: List[int] = field(metadata={"perde_ge": 0})
+
+    with pytest.raises(perde.InvalidAttributeError) as e:
+        m.loads_as(ConstrainedList, m.dumps({"a": [1]}))
+    assert e.value.args[0] == (
+        "invalid argument: invalid constraints of field `a`: "
+        "cannot use `perde_ge` with `list`"
+    )
+
+    @dataclass
+    class ConstrainedInt:
+        a: int = field(metadata={"perde_pattern": "[0-9]+"})
+
+    with pytest.raises(perde.InvalidAttributeError) as e:
+        m.loads_as(ConstrainedInt, m.dumps({"a": 1}))
+    assert e.value.args[0] == (
+        "invalid argument: invalid constraints of field `a`: "
+        "cannot use `perde_pattern` with `int`"
+    )
+
+    @dataclass
+    class ConstrainedNegative:
+        a: str = field(metadata={"perde_max_len": -1})
+
+    with pytest.raises(perde.InvalidAttributeError) as e:
+        m.loads_as(ConstrainedNegative, m.dumps({"a": "x"}))
+    assert e.value.args[0] == (
+        "invalid argument: invalid attribute `perde_max_len`: "
+        "the length must not be negative: got -1"
+    )
+
+    @dataclass
+    class ConstrainedRegex:
+        a: str = field(metadata={"perde_pattern": "("})
+
+    with pytest.raises(perde.InvalidAttributeError) as e:
+        m.loads_as(ConstrainedRegex, m.dumps({"a": "x"}))
+    assert e.value.args[0].startswith(
+        "invalid argument: invalid attribute `perde_pattern`: error: missing )"
+    )
+
+    @dataclass
+    class ConstrainedBound:
+        a: int = field(metadata={"perde_ge": "a"})
+
+    with pytest.raises(perde.InvalidAttributeError) as e:
+        m.loads_as(ConstrainedBound, m.dumps({"a": 1}))
+    assert e.value.args[0] == (
+        "invalid argument: invalid constraints of field `a`: "
+        "`perde_ge` of 'a' cannot be compared with `int`"
+    )
+
+    @dataclass
+    class ConstrainedDateBound:
+        a: datetime.date = field(metadata={"perde_le": datetime.datetime(2000, 1, 1)})
+
+    with pytest.raises(perde.InvalidAttributeError):
+        m.loads_as(ConstrainedDateBound, m.dumps({"a": "2000-01-01"}))
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_constraints_deserialize_with(m):
+    @dataclass
+    class ConstrainedHook:
+        a: int = field(
+            metadata={"perde_ge": 0, "perde_deserialize_with": lambda v: int(v, 16)}
+        )
+
+    with pytest.raises(perde.InvalidAttributeError) as e:
+        m.loads_as(ConstrainedHook, m.dumps({"a": "ff"}))
+    assert e.value.args[0] == (
+        "invalid argument: the constraints of field `a` "
+        "cannot be used with `perde_deserialize_with`"
+    )
@@ -1,6 +1,7 @@
 from dataclasses import dataclass, field
 import perde
 import pytest
+import sys
 from util import FORMATS
 
 """rust
@@ -200,3 +201,100 @@ def test_skip_deserializing_with_default(m):
     assert p == q
     assert p == Skip("ssssss", 0, 1.1, "a", "b")
     assert m.dumps(p) == m.data("SkipDefault")
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct InitFalse {
+  x: String,
+  y: i64,
+  z: i64,
+}
+
+add!(InitFalse {"xxx".into(), 3, 6});
+"""
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_field_init_false(m):
+    @dataclass
+    class InitFalse:
+        x: str
+        y: int
+        z: int = field(init=False, default=0)
+
+    p = m.unpack_data("InitFalse", astype=InitFalse)
+    assert p.x == "xxx" and p.y == 3 and p.z == 6
+    assert m.dumps(p) == m.data("InitFalse")
+
+    @dataclass
+    class NoZ:
+        x: str
+        y: int
+
+    p = m.loads_as(InitFalse, m.dumps(NoZ("xxx", 3)))
+    assert p.z == 0
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_field_init_false_derived(m):
+    @dataclass(frozen=True)
+    class InitFalse:
+        x: str
+        y: int
+        z: int = field(init=False, metadata={"perde_skip_deserializing": True})
+
+        def __post_init__(self):
+            object.__setattr__(self, "z", self.y * 3)
+
+    p = m.unpack_data("InitFalse", astype=InitFalse)
+    assert p.z == 9
+
+    @dataclass(frozen=True)
+    class InitFalse:
+        x: str
+        y: int
+        z: int = field(init=False, default=0)
+
+    p = m.unpack_data("InitFalse", astype=InitFalse)
+    assert p.z == 6
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct KwOnly {
+  a: i64,
+  b: String,
+  c: i64,
+  d: String,
+}
+
+add!(KwOnly {3, "xxx".into(), 4, "yyy".into()});
+"""
+
+
+@pytest.mark.skipif(sys.version_info < (3, 10), reason="requires python 3.10")
+@pytest.mark.parametrize("m", FORMATS)
+def test_field_kw_only(m):
+    from dataclasses import KW_ONLY
+
+    @dataclass
+    class KwOnly:
+        a: int = field(kw_only=True)
+        b: str
+        _: KW_ONLY
+        c: int = 10
+        d: str
+
+    p = m.unpack_data("KwOnly", astype=KwOnly)
+    assert p == KwOnly("xxx", a=3, c=4, d="yyy")
+    assert m.dumps(p) == m.data("KwOnly")
+
+    @dataclass(kw_only=True)
+    class KwOnly:
+        a: int
+        b: str
+        c: int
+        d: str
+
+    m.repack_type(KwOnly)
@@ -1,6 +1,7 @@
 import enum
 from dataclasses import dataclass, field
 import pytest
+import sys
 import perde
 import typing
 from util import FORMATS, FORMATS_EXCEPT, FORMATS_ONLY
@@ -54,8 +55,8 @@ def test_error_keyword_args(m):
     assert e.value.args[0] == "loads() takes no keyword arguments"
 
     with pytest.raises(TypeError) as e:
-        m.loads_as(kw="a")
-    assert e.value.args[0] == "loads_as() takes no keyword arguments"
+        m.loads_as(str, "a", kw="a")
+    assert e.value.args[0] == "loads_as() got an unexpected keyword argument `kw`"
 
 
 @pytest.mark.parametrize("m", FORMATS)
@@ -76,7 +77,7 @@ def test_error_dumps_wrong_number_of_args(m):
     assert e.value.args[0] == "loads_as() requires 2 positional arguments but got 3"
 
 
-@pytest.mark.parametrize("m", FORMATS_EXCEPT("msgpack"))
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("msgpack", "cbor"))
 def test_error_loads_invalid_argument_type(m):
     with pytest.raises(TypeError) as e:
         m.loads(b"a")
@@ -104,7 +105,7 @@ def test_error_loads_invalid_argument_type(m):
     )
 
 
-@pytest.mark.parametrize("m", FORMATS_ONLY("msgpack"))
+@pytest.mark.parametrize("m", FORMATS_ONLY("msgpack", "cbor"))
 def test_error_loads_invalid_argument_type_msgpack(m):
     with pytest.raises(TypeError) as e:
         m.loads("a")
@@ -132,6 +133,7 @@ def test_error_invalid_class_attribute(m):
             e.value.args[0]
             == f"invalid attribute `{attr}`: expected `{ty}` got `int`: 3"
         )
+        assert isinstance(e.value, perde.InvalidAttributeError)
 
     for attr in ["rename_all", "rename_all_serialize", "rename_all_deserialize"]:
         with pytest.raises(ValueError) as e:
@@ -145,6 +147,7 @@ def test_error_invalid_class_attribute(m):
             e.value.args[0]
             == f"invalid attribute `{attr}`: invalid string case: `hage`"
         )
+        assert isinstance(e.value, perde.InvalidAttributeError)
 
 
 @pytest.mark.parametrize("m", FORMATS)
@@ -235,6 +238,7 @@ def test_error_unsupported_type(m):
         "invalid argument: unsupported type "
         "`<class 'test_error.test_error_unsupported_type.<locals>.Abc'>`"
     )
+    assert isinstance(e.value, perde.UnsupportedTypeError)
 
 
 """rust
@@ -259,6 +263,8 @@ def test_error_decode_type_mismatch(m):
         m.loads_as(TypeMismatch, m.data("TypeMismatch"))
 
     print(f"{m.name}: {e}")
+    assert e.value.path == "b"
+    assert isinstance(e.value, perde.InvalidTypeError)
 
 
 """rust
@@ -282,6 +288,8 @@ def test_error_decode_missing_member(m):
         m.loads_as(MissingMember, m.data("MissingMember"))
 
     print(f"{m.name}: {e}")
+    assert e.value.path == "b"
+    assert isinstance(e.value, perde.MissingFieldError)
 
 
 """rust
@@ -308,6 +316,143 @@ def test_error_decode_too_many_member(m):
         m.loads_as(TooManyMember, m.data("TooManyMember"))
 
     print(f"{m.name}: {e}")
+    assert e.value.path == "c"
+    assert isinstance(e.value, perde.UnknownFieldError)
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct ErrorPathItem {
+  price: String,
+}
+
+#[derive(Serialize, Debug, new)]
+struct ErrorPathOrder {
+  id: String,
+  items: Vec<ErrorPathItem>,
+}
+
+#[derive(Serialize, Debug, new)]
+struct ErrorPath {
+  order: ErrorPathOrder,
+}
+
+add!(ErrorPath {
+  ErrorPathOrder::new("x".into(), vec![ErrorPathItem::new("a".into()), ErrorPathItem::new("b".into())])
+});
+"""
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_error_decode_path(m):
+    @dataclass
+    class Item:
+        price: int
+
+    @dataclass
+    class Order:
+        id: str
+        items: typing.List[Item]
+
+    @dataclass
+    class ErrorPath:
+        order: Order
+
+    with pytest.raises(m.errtype) as e:
+        m.loads_as(ErrorPath, m.data("ErrorPath"))
+
+    print(f"{m.name}: {e}")
+    assert e.value.path == "order.items[0].price"
+
+    with pytest.raises(m.errtype) as e:
+        m.loads_as(ErrorPath, m.data("TypeMismatch"))
+
+    print(f"{m.name}: {e}")
+    assert e.value.path == "order"
+
+
+def test_error_decode_path_key():
+    # The keys are shown by `repr()`.
+    with pytest.raises(perde.json.JsonError) as e:
+        perde.json.loads_as(typing.Dict[str, int], '{"a\\"b": "x"}')
+    assert e.value.path == "['a\"b']"
+
+    with pytest.raises(perde.msgpack.MsgpackError) as e:
+        perde.msgpack.loads_as(typing.Dict[int, int], perde.msgpack.dumps({1: "x"}))
+    assert e.value.path == "[1]"
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_error_decode_collect(m):
+    @dataclass
+    class Item:
+        price: int
+
+    @dataclass
+    class Order:
+        id: str
+        items: typing.List[Item]
+        note: str
+
+    @dataclass
+    class ErrorPath:
+        order: Order
+
+    with pytest.raises(m.errtype) as e:
+        m.loads_as(ErrorPath, m.data("ErrorPath"), collect_errors=True)
+
+    print(f"{m.name}: {e}")
+    assert [(p, k) for p, _, k in e.value.errors] == [
+        ("order.items[0].price", "invalid_type"),
+        ("order.items[1].price", "invalid_type"),
+        ("order.note", "missing_field"),
+    ]
+    assert e.value.args[0].startswith("found 3 error(s)\n")
+
+    with pytest.raises(m.errtype) as e:
+        m.loads_as(ErrorPath, m.data("ErrorPath"))
+
+    assert e.value.errors is None
+
+
+@pytest.mark.parametrize("m", FORMATS_ONLY("json", "msgpack"))
+def test_error_decode_collect_buffered(m):
+    # The input is buffered to collect the errors, which keeps every type of value.
+    @dataclass
+    class Buffered:
+        b: bool
+        i: int
+        f: float
+        s: str
+        n: typing.Optional[int]
+        l: typing.List[int]
+        d: typing.Dict[str, int]
+
+    v = Buffered(True, -1, 0.5, "x", None, [1], {"a": 1})
+    assert m.loads_as(Buffered, m.dumps(v), collect_errors=True) == v
+
+
+def test_error_decode_collect_buffered_binary():
+    m = perde.msgpack
+    assert m.loads_as(bytes, m.dumps(b"y"), collect_errors=True) == b"y"
+    assert m.loads_as(int, b"\xcf" + b"\xff" * 8, collect_errors=True) == 2 ** 64 - 1
+
+
+def test_error_decode_collect_nested():
+    # The hook calling `loads_as` doesn't reset the collected errors.
+    @dataclass
+    class Nested:
+        a: int
+        h: int = field(
+            metadata={"perde_deserialize_with": lambda s: perde.json.loads_as(int, s)}
+        )
+        b: int
+
+    with pytest.raises(perde.json.JsonError) as e:
+        s = '{"a": "x", "h": "3", "b": "y"}'
+        perde.json.loads_as(Nested, s, collect_errors=True)
+
+    assert [p for p, _, _ in e.value.errors] == ["a", "b"]
 
 
 """rust
@@ -339,6 +484,7 @@ def test_error_encode_skipped_enum(m):
         m.dumps(SkipEnumError(3, E.B))
 
     assert e.value.args[0] == "variant `B` is marked as `skip` and cannot be serialized"
+    assert isinstance(e.value, perde.SkippedVariantError)
 
     class E2(perde.Enum):
         A = 1
@@ -358,6 +504,121 @@ def test_error_encode_skipped_enum(m):
     assert e.value.args[0] == "variant `B` is marked as `skip` and cannot be serialized"
 
 
+@pytest.mark.parametrize("m", FORMATS)
+def test_error_hierarchy(m):
+    assert issubclass(m.errtype, perde.Error)
+    assert issubclass(m.package.DecodeError, m.errtype)
+    assert issubclass(m.package.DecodeError, perde.DecodeError)
+    assert issubclass(m.package.MissingFieldError, m.package.DecodeError)
+    assert issubclass(m.package.MissingFieldError, perde.MissingFieldError)
+    assert issubclass(m.package.EncodeError, perde.EncodeError)
+    assert issubclass(perde.SchemaError, perde.Error)
+
+    class E(perde.Enum):
+        X = 1
+
+    @dataclass
+    class SkipEnumError:
+        x: int
+        e: E
+
+    with pytest.raises(perde.UnknownVariantError) as e:
+        m.loads_as(SkipEnumError, m.data("SkipEnumError"))
+    assert isinstance(e.value, m.package.DecodeError)
+    assert e.value.path == "e"
+
+    @dataclass
+    class UnionMismatch:
+        x: typing.Union[int, str]
+
+    with pytest.raises(perde.UnionMismatchError) as e:
+        m.dumps(UnionMismatch(b"a"))
+    assert isinstance(e.value, m.package.EncodeError)
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_error_tagged_union(m):
+    @dataclass
+    class Cat:
+        name: str
+
+    @dataclass
+    class Dog:
+        name: str
+
+    @dataclass
+    class Tagged:
+        a: typing.Union[Cat, Dog] = field(metadata={"perde_tag": "type"})
+
+    with pytest.raises(m.package.UnknownVariantError) as e:
+        m.loads_as(Tagged, m.dumps({"a": {"type": "Cow", "name": "x"}}))
+    assert 'unknown variant `Cow`, expected one of ["Cat", "Dog"]' in e.value.args[0]
+    assert e.value.path == "a"
+
+    with pytest.raises(m.package.MissingFieldError) as e:
+        m.loads_as(Tagged, m.dumps({"a": {"name": "x"}}))
+    assert "missing field `type`" in e.value.args[0]
+    assert e.value.path == "a"
+
+    with pytest.raises(perde.InvalidAttributeError) as e:
+
+        @dataclass
+        class InvalidTag:
+            a: typing.Union[int, Dog] = field(metadata={"perde_tag": "type"})
+
+        m.loads_as(InvalidTag, m.dumps({"a": {"type": "Dog", "name": "x"}}))
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_error_untagged_union(m):
+    @dataclass
+    class Point2D:
+        x: int
+        y: int
+
+    @dataclass
+    class Untagged:
+        a: typing.Union[Point2D, int] = field(metadata={"perde_untagged": True})
+
+    with pytest.raises(m.package.DecodeError) as e:
+        m.loads_as(Untagged, m.dumps({"a": {"x": 1}}))
+    assert (
+        'data did not match any variant of untagged union ["Point2D", "int"]'
+        in e.value.args[0]
+    )
+    assert e.value.path == "a"
+
+
+@pytest.mark.skipif(sys.version_info < (3, 8), reason="requires python 3.8")
+@pytest.mark.parametrize("m", FORMATS)
+def test_error_literal(m):
+    from typing import Literal
+
+    @dataclass
+    class Circle:
+        kind: Literal["circle"]
+        radius: int
+
+    @dataclass
+    class Rect:
+        kind: Literal["rect", "square"]
+        width: int
+
+    with pytest.raises(m.package.DecodeError) as e:
+        m.loads_as(Rect, m.dumps({"kind": "triangle", "width": 1}))
+    assert (
+        "the literal value must be any of ['rect', 'square']: got 'triangle'"
+        in e.value.args[0]
+    )
+    assert e.value.path == "kind"
+
+    with pytest.raises(m.package.DecodeError) as e:
+        m.loads_as(typing.Union[Circle, Rect], m.dumps({"kind": "x", "width": 1}))
+    assert 'data did not match any variant of union ["Circle", "Rect"]' in (
+        e.value.args[0]
+    )
+
+
 """rust
 #[derive(Serialize, Debug, new)]
 struct DictFlattenMsgpack {
@@ -389,5 +650,57 @@ def test_error_dict_flatten_msgpack(m):
         with pytest.raises(m.errtype) as e:
             m.dumps(d)
         print(e)
+    elif m.fmtname == "cbor":
+        # The length is unknown, so it makes an indefinite-length map.
+        assert m.loads(m.dumps(d)) == m.loads(m.data("DictFlattenMsgpack"))
     else:
         assert m.dumps(d) == m.data("DictFlattenMsgpack")
+
+
+def test_error_position():
+    @dataclass
+    class Position:
+        a: int
+        b: str
+
+    with pytest.raises(perde.json.JsonError) as e:
+        perde.json.loads_as(Position, '{"a": 1,\n "b": 3}')
+    assert (e.value.line, e.value.column, e.value.offset) == (2, 7, 15)
+
+    with pytest.raises(perde.json.JsonError) as e:
+        perde.json.loads('{"a": 1,\n "b": }')
+    assert (e.value.line, e.value.column, e.value.offset) == (2, 7, 15)
+
+    with pytest.raises(perde.json.JsonError) as e:
+        perde.json.loads_as_lines(Position, '{"a": 1, "b": "x"}\n\n{"a": 2, "b": 3}')
+    assert (e.value.line, e.value.column, e.value.offset) == (3, 15, 34)
+    assert e.value.path == "[1].b"
+    assert str(e.value).startswith("value at line 3: ")
+
+    # The line where the value starts is reported as well.
+    with pytest.raises(perde.json.JsonError) as e:
+        s = '{"a": 1, "b": "x"}\r\n{"a": 2,\r\n "b": 3}'
+        perde.json.loads_as_lines(Position, s)
+    assert (e.value.line, e.value.column) == (3, 7)
+    assert e.value.path == "[1].b"
+    assert str(e.value).startswith("value at line 2: ")
+
+    with pytest.raises(perde.yaml.YamlError) as e:
+        perde.yaml.loads_as(Position, "a: 1\nb: [3]")
+    assert (e.value.line, e.value.column, e.value.offset) == (2, 4, 8)
+
+    with pytest.raises(perde.yaml.YamlError) as e:
+        perde.yaml.loads("a: 1\nb: [")
+    assert (e.value.line, e.value.column, e.value.offset) == (3, 1, 9)
+
+    with pytest.raises(perde.toml.TomlError) as e:
+        perde.toml.loads_as(Position, "a = 1\nb = 3")
+    assert (e.value.line, e.value.column, e.value.offset) == (2, 5, 10)
+
+    with pytest.raises(perde.toml.TomlError) as e:
+        perde.toml.loads("a = 1\nb = ")
+    assert (e.value.line, e.value.column, e.value.offset) == (2, 5, 10)
+
+    with pytest.raises(perde.msgpack.MsgpackError) as e:
+        perde.msgpack.loads_as(Position, b"\x81\xa1a\xa1x")
+    assert (e.value.line, e.value.column, e.value.offset) == (None, None, None)
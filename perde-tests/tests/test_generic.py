@@ -0,0 +1,162 @@
+from dataclasses import dataclass, field
+from typing import Dict, Generic, List, Optional, TypeVar
+import perde
+import pytest
+from util import FORMATS
+
+T = TypeVar("T")
+U = TypeVar("U")
+
+
+@dataclass
+class GenericUser:
+    name: str
+    age: int
+
+
+@dataclass
+class GenericPage(Generic[T]):
+    total: int
+    items: List[T]
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct GenericUser {
+  name: String,
+  age: i64,
+}
+
+#[derive(Serialize, Debug, new)]
+struct GenericPage {
+  total: i64,
+  items: Vec<GenericUser>,
+}
+
+add!(GenericPage {
+     2,
+     vec![GenericUser::new("xxx".into(), 3), GenericUser::new("yyy".into(), 4)]
+    });
+"""
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_generic(m):
+    m.repack_data("GenericPage", GenericPage[GenericUser])
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_generic_parameterizations(m):
+    m.repack_as(GenericPage[int], GenericPage(2, [1, 2]))
+    m.repack_as(GenericPage[str], GenericPage(1, ["x"]))
+    with pytest.raises(m.package.DecodeError):
+        m.loads_as(GenericPage[int], m.dumps(GenericPage(1, ["x"])))
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_generic_unbound(m):
+    m.repack_as(GenericPage, GenericPage(2, [{"a": 1}, {"b": 2}]))
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+#[serde(rename_all = "camelCase")]
+struct GenericPair {
+  first_value: i64,
+  second_values: HashMap<String, Vec<String>>,
+}
+
+add!(GenericPair {
+     1,
+     vec![("a".into(), vec!["b".into(), "c".into()])].into_iter().collect()
+    });
+"""
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_generic_multiple_params(m):
+    @perde.attr(rename_all="camelCase")
+    @dataclass
+    class GenericPair(Generic[T, U]):
+        first_value: T
+        second_values: Dict[str, List[U]] = field(default_factory=dict)
+
+    m.repack_data("GenericPair", GenericPair[int, str])
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct GenericTree {
+  value: String,
+  children: Vec<GenericTree>,
+}
+
+add!(GenericTree {
+     "a".into(),
+     vec![
+       GenericTree::new("b".into(), vec![]),
+       GenericTree::new("c".into(), vec![GenericTree::new("d".into(), vec![])]),
+     ]
+    });
+"""
+
+
+@dataclass
+class GenericTree(Generic[T]):
+    value: T
+    children: List["GenericTree[T]"]
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_generic_recursive(m):
+    m.repack_data("GenericTree", GenericTree[str])
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct GenericNested {
+  next: Option<i64>,
+  page: GenericPage,
+}
+
+add!(GenericNested {
+     Some(2),
+     GenericPage::new(1, vec![GenericUser::new("xxx".into(), 3)])
+    });
+"""
+
+
+@dataclass
+class GenericNested(Generic[T]):
+    next: Optional[int]
+    page: GenericPage[T]
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_generic_nested(m):
+    m.repack_data("GenericNested", GenericNested[GenericUser])
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_generic_subclass(m):
+    @dataclass
+    class UserPage(GenericPage[GenericUser]):
+        pass
+
+    v = UserPage(1, [GenericUser("xxx", 3)])
+    m.repack_as(UserPage, v)
+    assert m.loads_as(UserPage, m.dumps(v)).items[0] == GenericUser("xxx", 3)
+
+    # The type variables are substituted through the base classes.
+    @dataclass
+    class ListPage(GenericPage[List[U]], Generic[U]):
+        pass
+
+    @dataclass
+    class IntListPage(ListPage[int]):
+        pass
+
+    m.repack_as(IntListPage, IntListPage(1, [[1, 2]]))
+    m.repack_as(ListPage[str], ListPage(1, [["x"]]))
+    with pytest.raises(m.package.DecodeError):
+        m.loads_as(IntListPage, m.dumps({"total": 1, "items": [["x"]]}))
@@ -0,0 +1,162 @@
+from dataclasses import dataclass
+import enum
+import sys
+from typing import List, Optional, Union
+import perde
+import pytest
+from util import FORMATS, FORMATS_EXCEPT
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct LiteralPrimitive {
+  a: String,
+  b: i64,
+  c: bool,
+  d: String,
+}
+
+add!(LiteralPrimitive {"yyy".into(), 2, true, "Blue".into()});
+"""
+
+
+@pytest.mark.skipif(sys.version_info < (3, 8), reason="requires python 3.8")
+@pytest.mark.parametrize("m", FORMATS)
+def test_literal(m):
+    from typing import Literal
+
+    class Color(enum.Enum):
+        Red = 1
+        Blue = 2
+
+    @dataclass
+    class LiteralPrimitive:
+        a: Literal["xxx", "yyy"]
+        b: Literal[1, 2, 3]
+        c: Literal[True]
+        d: Literal[Color.Blue]
+
+    p = m.unpack_data("LiteralPrimitive", astype=LiteralPrimitive)
+    assert p == LiteralPrimitive("yyy", 2, True, Color.Blue)
+    assert m.dumps(p) == m.data("LiteralPrimitive")
+
+
+@pytest.mark.skipif(sys.version_info < (3, 8), reason="requires python 3.8")
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_literal_none(m):
+    from typing import Literal
+
+    @dataclass
+    class LiteralNone:
+        a: Literal["xxx", None]
+        b: Optional[Literal[3]]
+
+    m.repack_as(LiteralNone, LiteralNone("xxx", 3))
+    m.repack_as(LiteralNone, LiteralNone(None, None))
+
+
+@pytest.mark.skipif(sys.version_info < (3, 8), reason="requires python 3.8")
+@pytest.mark.parametrize("m", FORMATS)
+def test_literal_enum_value(m):
+    from typing import Literal
+
+    @perde.attr(as_value=True)
+    class Level(enum.Enum):
+        Low = 1
+        High = 2
+
+    @dataclass
+    class LiteralEnumValue:
+        a: Literal[Level.High]
+
+    assert m.dumps(LiteralEnumValue(Level.High)) == m.dumps({"a": 2})
+    m.repack_as(LiteralEnumValue, LiteralEnumValue(Level.High))
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct LiteralCircle {
+  kind: String,
+  radius: i64,
+}
+
+#[derive(Serialize, Debug, new)]
+struct LiteralRect {
+  kind: String,
+  width: i64,
+  height: i64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(untagged)]
+enum LiteralShape {
+  Circle(LiteralCircle),
+  Rect(LiteralRect),
+}
+
+#[derive(Serialize, Debug, new)]
+struct LiteralUnion {
+  shapes: Vec<LiteralShape>,
+}
+
+add!(LiteralUnion {
+     vec![
+       LiteralShape::Rect(LiteralRect::new("rect".into(), 1, 2)),
+       LiteralShape::Circle(LiteralCircle::new("circle".into(), 3)),
+     ]
+    });
+"""
+
+
+@pytest.mark.skipif(sys.version_info < (3, 8), reason="requires python 3.8")
+@pytest.mark.parametrize("m", FORMATS)
+def test_literal_union(m):
+    from typing import Literal
+
+    @dataclass
+    class Circle:
+        kind: Literal["circle"]
+        radius: int
+
+    @dataclass
+    class Rect:
+        kind: Literal["rect"]
+        width: int
+        height: int
+
+    @dataclass
+    class LiteralUnion:
+        shapes: List[Union[Circle, Rect]]
+
+    p = m.unpack_data("LiteralUnion", astype=LiteralUnion)
+    assert p == LiteralUnion([Rect("rect", 1, 2), Circle("circle", 3)])
+    assert m.dumps(p) == m.data("LiteralUnion")
+
+
+@pytest.mark.skipif(sys.version_info < (3, 8), reason="requires python 3.8")
+@pytest.mark.parametrize("m", FORMATS)
+def test_literal_union_primitive(m):
+    from typing import Literal
+
+    @dataclass
+    class LiteralUnionPrimitive:
+        a: Union[Literal["auto"], int]
+
+    m.repack_as(LiteralUnionPrimitive, LiteralUnionPrimitive("auto"))
+    m.repack_as(LiteralUnionPrimitive, LiteralUnionPrimitive(10))
+
+
+@pytest.mark.skipif(sys.version_info < (3, 8), reason="requires python 3.8")
+@pytest.mark.parametrize("m", FORMATS)
+def test_literal_encode_invalid(m):
+    from typing import Literal
+
+    @dataclass
+    class LiteralInvalid:
+        a: Literal["xxx", 1]
+
+    for v, got in [("yyy", "'yyy'"), (True, "True"), (None, "None")]:
+        with pytest.raises(m.package.EncodeError) as e:
+            m.dumps(LiteralInvalid(v))
+        assert e.value.args[0] == (
+            f"the literal value must be any of ['xxx', 1]: got {got}"
+        )
@@ -0,0 +1,90 @@
+from dataclasses import dataclass, field
+import sys
+from typing import List, NewType, Optional
+import perde
+import pytest
+from util import FORMATS, FORMATS_EXCEPT
+
+UserId = NewType("UserId", int)
+UserIds = NewType("UserIds", List[UserId])
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct NewTypeField {
+  id: i64,
+  friends: Vec<i64>,
+  parent: Option<i64>,
+}
+
+add!(NewTypeField {3, vec![4, 5], Some(6)});
+"""
+
+
+@dataclass
+class NewTypeField:
+    id: UserId
+    friends: UserIds
+    parent: Optional[UserId]
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_new_type(m):
+    p = m.unpack_data("NewTypeField", astype=NewTypeField)
+    assert p == NewTypeField(UserId(3), UserIds([UserId(4), UserId(5)]), UserId(6))
+    assert type(p.id) is int
+    assert m.dumps(p) == m.data("NewTypeField")
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_new_type_top_level(m):
+    assert m.loads_as(UserId, m.dumps(10)) == 10
+    assert m.loads_as(UserIds, m.dumps([1, 2])) == [1, 2]
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct AnnotatedField {
+  id: i64,
+  #[serde(rename = "userName")]
+  name: String,
+  #[serde(rename = "labels")]
+  tags: Vec<String>,
+}
+
+add!(AnnotatedField {3, "xxx".into(), vec!["a".into(), "b".into()]});
+"""
+
+
+@pytest.mark.skipif(sys.version_info < (3, 9), reason="requires python 3.9")
+@pytest.mark.parametrize("m", FORMATS)
+def test_annotated(m):
+    from typing import Annotated
+
+    @dataclass
+    class AnnotatedField:
+        id: Annotated[int, "the id"]
+        name: Annotated[str, {"perde_rename": "userName"}]
+        tags: Annotated[List[str], {"perde_rename": "tags"}] = field(
+            default_factory=list, metadata={"perde_rename": "labels"}
+        )
+        note: Annotated[str, {"perde_skip": True}] = "memo"
+
+    m.repack_type(AnnotatedField)
+
+
+@pytest.mark.skipif(sys.version_info < (3, 9), reason="requires python 3.9")
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_annotated_top_level(m):
+    from typing import Annotated
+
+    assert m.loads_as(Annotated[List[int], "x"], m.dumps([1, 2])) == [1, 2]
+
+    # The attributes can be given only to the fields of classes.
+    ge = Annotated[int, {"perde_ge": 0}]
+    for t, v in [(ge, -1), (List[ge], [-1])]:
+        with pytest.raises(perde.InvalidAttributeError) as e:
+            m.loads_as(t, m.dumps(v))
+        assert e.value.args[0].endswith(
+            "the attributes in `typing.Annotated[int, {'perde_ge': 0}]` "
+            "can be given only to the fields of classes"
+        )
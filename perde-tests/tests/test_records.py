@@ -0,0 +1,239 @@
+from collections import namedtuple
+from dataclasses import dataclass
+import sys
+from typing import List, NamedTuple, Optional, Union
+import attr
+import perde
+import pytest
+from util import FORMATS, FORMATS_EXCEPT
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct AttrsClass {
+  id: i64,
+  #[serde(rename = "userName")]
+  name: String,
+  tags: Vec<String>,
+}
+
+add!(AttrsClass {3, "xxx".into(), vec!["a".into(), "b".into()]});
+"""
+
+
+@attr.s(auto_attribs=True)
+class AttrsClass:
+    id: int
+    name: str = attr.ib(metadata={"perde_rename": "userName"})
+    tags: List[str] = attr.Factory(list)
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_attrs_class(m):
+    m.repack_type(AttrsClass)
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_attrs_default(m):
+    @attr.s
+    class Untyped:
+        x = attr.ib()
+        y = attr.ib(default=3)
+        z = attr.ib(factory=list)
+
+    assert m.loads_as(Untyped, m.dumps({"x": "a"})) == Untyped("a", 3, [])
+    assert m.dumps(Untyped(1, 2, [3])) == m.dumps({"x": 1, "y": 2, "z": [3]})
+
+
+def test_attrs_factory_takes_self():
+    @attr.s
+    class TakesSelf:
+        x = attr.ib()
+        y = attr.ib(default=attr.Factory(lambda s: s.x, takes_self=True))
+
+    with pytest.raises(perde.InvalidAttributeError) as e:
+        perde.json.loads_as(TakesSelf, '{"x": 1}')
+    assert "takes_self" in str(e.value)
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_attrs_private_kw_only(m):
+    # attrs strips the leading underscore in the constructor.
+    @attr.s(auto_attribs=True)
+    class Private:
+        x: int
+        _p: int = attr.ib(kw_only=True)
+        _q: int = attr.ib(kw_only=True, alias="r")
+
+    v = Private(1, p=2, r=3)
+    assert m.dumps(v) == m.dumps({"x": 1, "_p": 2, "_q": 3})
+    assert m.loads_as(Private, m.dumps(v)) == v
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct NamedTupleMap {
+  x: i64,
+  label: String,
+}
+
+add!(NamedTupleMap {3, "p".into()});
+"""
+
+
+class NamedTupleMap(NamedTuple):
+    x: int
+    label: str = "origin"
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_named_tuple(m):
+    m.repack_type(NamedTupleMap)
+    assert m.loads_as(NamedTupleMap, m.dumps({"x": 1})) == NamedTupleMap(1)
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_named_tuple_untyped(m):
+    Pair = namedtuple("Pair", ["a", "b"], defaults=[0])
+
+    assert m.loads_as(Pair, m.dumps({"a": "x"})) == Pair("x", 0)
+    assert m.dumps(Pair(1, 2)) == m.dumps({"a": 1, "b": 2})
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct NamedTupleArray {
+  point: (i64, String),
+}
+
+add!(NamedTupleArray {(3, "p".into())});
+"""
+
+
+@perde.attr(as_array=True)
+class Point(NamedTuple):
+    x: int
+    label: str = "origin"
+
+
+@dataclass
+class NamedTupleArray:
+    point: Point
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_as_array(m):
+    m.repack_type(NamedTupleArray)
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_as_array_length(m):
+    assert m.loads_as(Point, m.dumps([1])) == Point(1)
+    assert m.loads_as(Point, m.dumps([1, "a", "b"])) == Point(1, "a")
+
+    with pytest.raises(m.package.MissingFieldError):
+        m.loads_as(Point, m.dumps([]))
+
+    @perde.attr(as_array=True, deny_unknown_fields=True)
+    @dataclass
+    class Strict:
+        x: int
+        y: Optional[int]
+
+    assert m.loads_as(Strict, m.dumps([1])) == Strict(1, None)
+    with pytest.raises(m.package.DecodeError):
+        m.loads_as(Strict, m.dumps([1, 2, 3]))
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct TypedDictUser {
+  id: i64,
+  name: String,
+  tags: Vec<String>,
+}
+
+add!(TypedDictUser {3, "xxx".into(), vec!["a".into()]});
+"""
+
+
+@pytest.mark.skipif(sys.version_info < (3, 8), reason="requires python 3.8")
+@pytest.mark.parametrize("m", FORMATS)
+def test_typed_dict(m):
+    from typing import TypedDict
+
+    class Base(TypedDict):
+        id: int
+        name: str
+
+    class TypedDictUser(Base, total=False):
+        tags: List[str]
+
+    m.repack_type(TypedDictUser)
+
+    d = m.loads_as(TypedDictUser, m.dumps({"name": "a", "id": 1, "age": 3}))
+    assert type(d) is dict
+    assert d == {"name": "a", "id": 1}
+
+    with pytest.raises(m.package.MissingFieldError):
+        m.loads_as(TypedDictUser, m.dumps({"name": "a"}))
+
+    @dataclass
+    class Wrapper:
+        user: TypedDictUser
+
+    assert m.dumps(Wrapper({"name": "a", "id": 1})) == m.dumps(
+        Wrapper({"id": 1, "name": "a"})
+    )
+    with pytest.raises(m.package.EncodeError):
+        m.dumps(Wrapper({"name": "a"}))
+
+
+@pytest.mark.skipif(sys.version_info < (3, 11), reason="requires python 3.11")
+@pytest.mark.parametrize("m", FORMATS)
+def test_typed_dict_not_required(m):
+    from typing import NotRequired, Required, TypedDict
+
+    class User(TypedDict):
+        id: int
+        name: NotRequired[str]
+
+    class Group(TypedDict, total=False):
+        id: Required[int]
+        users: List[User]
+
+    v = {"id": 1, "users": [{"id": 2}, {"id": 3, "name": "x"}]}
+    assert m.loads_as(Group, m.dumps(v)) == v
+
+    with pytest.raises(m.package.MissingFieldError):
+        m.loads_as(Group, m.dumps({"users": []}))
+
+
+@pytest.mark.skipif(sys.version_info < (3, 8), reason="requires python 3.8")
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_typed_dict_union(m):
+    from typing import TypedDict
+
+    class Point(TypedDict):
+        x: int
+        y: int
+
+    class Label(TypedDict, total=False):
+        text: str
+
+    @dataclass
+    class Shape:
+        p: Optional[Point]
+        q: Union[Point, int]
+
+    # The dicts are matched by the keys, as typed dicts have no type of their own.
+    m.repack_as(Shape, Shape({"x": 1, "y": 2}, {"x": 3, "y": 4}))
+    m.repack_as(Shape, Shape(None, 3))
+    with pytest.raises(m.package.EncodeError):
+        m.dumps(Shape({"x": 1}, 3))
+
+    @dataclass
+    class Labeled:
+        r: Union[Point, Label]
+
+    assert m.loads(m.dumps(Labeled({"text": "a"}))) == {"r": {"text": "a"}}
+    assert m.loads(m.dumps(Labeled({}))) == {"r": {}}
@@ -0,0 +1,222 @@
+from dataclasses import dataclass, field
+from typing import Dict, List, Optional, Union
+import perde
+import pytest
+from util import FORMATS, FORMATS_EXCEPT
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct RecursiveTree {
+  value: i64,
+  children: Vec<RecursiveTree>,
+}
+
+add!(RecursiveTree {
+     1,
+     vec![
+       RecursiveTree::new(2, vec![]),
+       RecursiveTree::new(3, vec![RecursiveTree::new(4, vec![])]),
+     ]
+    });
+"""
+
+
+@dataclass
+class RecursiveTree:
+    value: int
+    children: List["RecursiveTree"]
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_recursive_tree(m):
+    m.repack_type(RecursiveTree)
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct RecursiveList {
+  value: i64,
+  next: Option<Box<RecursiveList>>,
+}
+
+add!(RecursiveList {
+     1,
+     Some(Box::new(RecursiveList::new(2, Some(Box::new(RecursiveList::new(3, None))))))
+    }
+    except "toml");
+"""
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_recursive_optional(m):
+    @dataclass
+    class RecursiveList:
+        value: int
+        next: Optional["RecursiveList"]
+
+    m.repack_type(RecursiveList)
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct MutualDir {
+  name: String,
+  entries: Vec<MutualEntry>,
+}
+
+#[derive(Serialize, Debug, new)]
+struct MutualEntry {
+  dir: Option<MutualDir>,
+  size: i64,
+}
+
+add!(MutualDir {
+     "root".into(),
+     vec![
+       MutualEntry::new(None, 3),
+       MutualEntry::new(Some(MutualDir::new("sub".into(), vec![])), 0),
+     ]
+    }
+    except "toml");
+"""
+
+
+@dataclass
+class MutualDir:
+    name: str
+    entries: List["MutualEntry"]
+
+
+@dataclass
+class MutualEntry:
+    dir: Optional[MutualDir]
+    size: int
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_mutually_recursive(m):
+    m.repack_type(MutualDir)
+
+
+"""rust
+#[derive(Serialize, Debug, new)]
+#[serde(rename_all = "camelCase")]
+struct RecursiveAttr {
+  node_name: String,
+  child_nodes: HashMap<String, RecursiveAttr>,
+}
+
+add!(RecursiveAttr {
+     "a".into(),
+     vec![("b".into(), RecursiveAttr::new("c".into(), HashMap::new()))]
+       .into_iter()
+       .collect()
+    });
+"""
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_recursive_attr(m):
+    @perde.attr(rename_all="camelCase")
+    @dataclass
+    class RecursiveAttr:
+        node_name: str
+        child_nodes: Dict[str, "RecursiveAttr"] = field(default_factory=dict)
+
+    m.repack_type(RecursiveAttr)
+
+
+@perde.attr(untagged=True)
+@dataclass
+class RecursiveLeaf:
+    value: int
+
+
+@perde.attr(untagged=True)
+@dataclass
+class RecursiveBranch:
+    left: Union[RecursiveLeaf, "RecursiveBranch"]
+    right: Union[RecursiveLeaf, "RecursiveBranch"]
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_recursive_union(m):
+    v = RecursiveBranch(
+        RecursiveLeaf(1), RecursiveBranch(RecursiveLeaf(2), RecursiveLeaf(3))
+    )
+    m.repack_as(Union[RecursiveLeaf, RecursiveBranch], v)
+
+
+@perde.attr(tag="type")
+@dataclass
+class RecursiveLit:
+    value: int
+
+
+@perde.attr(tag="type")
+@dataclass
+class RecursiveAdd:
+    lhs: Union[RecursiveLit, "RecursiveAdd"]
+    rhs: Union[RecursiveLit, "RecursiveAdd"]
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_recursive_tagged_union(m):
+    v = RecursiveAdd(RecursiveLit(1), RecursiveAdd(RecursiveLit(2), RecursiveLit(3)))
+    m.repack_as(RecursiveAdd, v)
+    assert v == m.loads_as(
+        Union[RecursiveLit, RecursiveAdd],
+        m.dumps(
+            {
+                "type": "RecursiveAdd",
+                "lhs": {"type": "RecursiveLit", "value": 1},
+                "rhs": {
+                    "type": "RecursiveAdd",
+                    "lhs": {"type": "RecursiveLit", "value": 2},
+                    "rhs": {"type": "RecursiveLit", "value": 3},
+                },
+            }
+        ),
+    )
+
+
+@dataclass
+class RecursiveConstrained:
+    value: int
+    parent: "RecursiveConstrained" = field(
+        default=None, metadata={"perde_pattern": "[a-z]+"}
+    )
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_recursive_invalid_constraints(m):
+    with pytest.raises(perde.InvalidAttributeError) as e:
+        m.loads_as(RecursiveConstrained, m.dumps({"value": 1}))
+    assert e.value.args[0] == (
+        "invalid argument: invalid constraints of field `parent`: "
+        "cannot use `perde_pattern` with `RecursiveConstrained`"
+    )
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_recursive_circular_reference(m):
+    @dataclass
+    class RecursiveList:
+        value: int
+        next: Optional["RecursiveList"]
+
+    n = RecursiveList(1, None)
+    n.next = n
+    with pytest.raises(m.package.EncodeError) as e:
+        m.dumps(n)
+    assert "circular reference detected" in str(e.value)
+
+    l = []
+    l.append(l)
+    with pytest.raises(m.package.EncodeError) as e:
+        m.dumps(l)
+    assert "circular reference detected" in str(e.value)
+
+    # The same object can appear more than once without a cycle.
+    child = RecursiveList(2, None)
+    assert m.loads(m.dumps([child, child])) == [{"value": 2, "next": None}] * 2
@@ -0,0 +1,163 @@
+from dataclasses import dataclass
+from decimal import Decimal
+import ipaddress
+import pathlib
+from typing import List, Optional, Union
+import perde
+import pytest
+from util import FORMATS, FORMATS_EXCEPT
+
+
+class Money:
+    def __init__(self, amount: Decimal, currency: str):
+        self.amount = amount
+        self.currency = currency
+
+    def __eq__(self, other):
+        return (self.amount, self.currency) == (other.amount, other.currency)
+
+
+class Float64(float):
+    pass
+
+
+perde.register(pathlib.Path, str, pathlib.Path, as_type=str)
+perde.register(ipaddress.IPv4Address, str, ipaddress.IPv4Address, as_type=str)
+perde.register(
+    Money,
+    lambda m: [str(m.amount), m.currency],
+    lambda v: Money(Decimal(v[0]), v[1]),
+    as_type=List[str],
+)
+perde.register(Float64, float, Float64, as_type=float)
+
+"""rust
+#[derive(Serialize, Debug, new)]
+struct Registered {
+  path: String,
+  ip: String,
+  price: Vec<String>,
+  ratio: f64,
+  paths: Vec<String>,
+}
+
+add!(Registered {
+     "/tmp/x".into(),
+     "10.0.0.1".into(),
+     vec!["1.5".into(), "USD".into()],
+     0.5,
+     vec!["a".into(), "b/c".into()]
+    });
+"""
+
+
+@dataclass
+class Registered:
+    path: pathlib.Path
+    ip: ipaddress.IPv4Address
+    price: Money
+    ratio: Float64
+    paths: List[pathlib.Path]
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_register(m):
+    p = m.unpack_data("Registered", astype=Registered)
+    assert p == Registered(
+        pathlib.Path("/tmp/x"),
+        ipaddress.IPv4Address("10.0.0.1"),
+        Money(Decimal("1.5"), "USD"),
+        Float64(0.5),
+        [pathlib.Path("a"), pathlib.Path("b/c")],
+    )
+    assert type(p.ratio) is Float64
+    assert m.dumps(p) == m.data("Registered")
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_register_top_level(m):
+    # The instances of the subclasses, e.g. `PosixPath`, are converted as well.
+    m.repack_as(pathlib.Path, pathlib.Path("x"))
+    m.repack_as(List[pathlib.Path], [pathlib.Path("x"), pathlib.Path("y")])
+    assert m.dumps(pathlib.Path("x")) == m.dumps("x")
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_register_union(m):
+    @dataclass
+    class RegisteredUnion:
+        a: Optional[pathlib.Path]
+        b: Union[int, pathlib.Path]
+
+    m.repack_as(RegisteredUnion, RegisteredUnion(None, 3))
+    m.repack_as(RegisteredUnion, RegisteredUnion(pathlib.Path("x"), pathlib.Path("y")))
+
+
+@pytest.mark.parametrize("m", FORMATS)
+def test_register_error(m):
+    class Failing:
+        pass
+
+    def fail(v):
+        raise ValueError("cannot convert")
+
+    perde.register(Failing, fail, fail, as_type=str)
+
+    @dataclass
+    class RegisteredError:
+        a: Failing
+
+    with pytest.raises(m.package.EncodeError) as e:
+        m.dumps(RegisteredError(Failing()))
+    assert e.value.args[0].startswith(
+        "error in the converter of `Failing`: ValueError: cannot convert"
+    )
+
+    with pytest.raises(m.package.DecodeError) as e:
+        m.loads_as(RegisteredError, m.dumps({"a": "x"}))
+    assert e.value.args[0].startswith(
+        "error in the converter of `Failing`: ValueError: cannot convert"
+    )
+    assert e.value.path == "a"
+
+
+def test_register_invalid():
+    with pytest.raises(perde.UnsupportedTypeError) as e:
+        perde.register(1, str, str, as_type=str)
+    assert e.value.args[0] == "`1` is not a type"
+
+    with pytest.raises(perde.SchemaError) as e:
+        perde.register(Money, str, None, as_type=str)
+    assert e.value.args[0] == "the converters of `Money` must be callable"
+
+    with pytest.raises(TypeError) as e:
+        perde.register(Money, str, str)
+    assert e.value.args[0] == "register() requires keyword argument `as_type`"
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_register_again(m):
+    class Celsius(float):
+        pass
+
+    class Kelvin(Celsius):
+        pass
+
+    perde.register(Celsius, float, Celsius, as_type=float)
+    assert m.dumps(Celsius(1.5)) == m.dumps(1.5)
+    assert m.dumps(Kelvin(1.5)) == m.dumps(1.5)
+
+    # The schemas already used by the type and its subclasses are replaced.
+    perde.register(Celsius, str, lambda v: Celsius(float(v)), as_type=str)
+    assert m.dumps(Celsius(1.5)) == m.dumps("1.5")
+    assert m.dumps(Kelvin(1.5)) == m.dumps("1.5")
+    assert m.loads_as(Kelvin, m.dumps("1.5")) == Celsius(1.5)
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_register_builtin(m):
+    # The schema of the built-in types can't be cached in the type.
+    perde.register(complex, str, complex, as_type=str)
+    for _ in range(2):
+        m.repack_as(complex, 1 + 2j)
+    assert m.dumps(1 + 2j) == m.dumps("(1+2j)")
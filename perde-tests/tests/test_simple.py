@@ -1,4 +1,6 @@
-from dataclasses import dataclass
+from dataclasses import dataclass, field
+import json
+import struct
 import sys
 import enum
 import typing
@@ -7,7 +9,7 @@ import perde
 import datetime
 import decimal
 import uuid
-from util import FORMATS, FORMATS_EXCEPT, repack, repack_as
+from util import FORMATS, FORMATS_EXCEPT, FORMATS_ONLY, repack, repack_as
 
 
 @pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
@@ -372,3 +374,397 @@ def test_decimal(m):
 @pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
 def test_uuid(m):
     repack_as(m, uuid.UUID, uuid.uuid1())
+
+
+@pytest.mark.parametrize("m", FORMATS_EXCEPT("toml"))
+def test_optional_native_types(m):
+    aware = datetime.datetime(2013, 3, 21, 20, 4, tzinfo=datetime.timezone.utc)
+    repack_as(m, typing.Optional[datetime.datetime], aware)
+    repack_as(m, typing.Optional[datetime.datetime], aware.replace(tzinfo=None))
+    repack_as(m, typing.Optional[datetime.date], aware.date())
+    repack_as(m, typing.Optional[decimal.Decimal], decimal.Decimal("-273.15"))
+    repack_as(m, typing.Optional[uuid.UUID], uuid.uuid4())
+    repack_as(m, typing.Optional[uuid.UUID], None)
+    repack_as(m, typing.Union[datetime.datetime, int], aware)
+    repack_as(m, typing.Union[datetime.datetime, int], 3)
+
+
+@pytest.mark.parametrize("m", FORMATS_ONLY("cbor"))
+def test_cbor_tags(m):
+    utc = datetime.timezone.utc
+    d = datetime.datetime(2013, 3, 21, 20, 4, tzinfo=utc)
+    assert m.dumps(d) == b"\xc0" + m.dumps(d.isoformat())
+    assert m.loads(m.dumps(d)) == d
+    assert m.loads_as(datetime.datetime, bytes.fromhex("c11a514b67b0")) == d
+    assert m.loads_as(datetime.datetime, b"\xc0\x742013-03-21T20:04:00Z") == d
+
+    # Naive datetimes are not RFC 3339.
+    naive = datetime.datetime(2013, 3, 21, 20, 4)
+    assert m.dumps(naive) == m.dumps(naive.isoformat())
+
+    dec = decimal.Decimal("273.15")
+    assert m.dumps(dec) == bytes.fromhex("c48221196ab3")
+    assert m.loads(m.dumps(dec)) == dec
+    for v in ["-1.5E+30", "123456789012345678901234567.8", "-0.000001"]:
+        repack_as(m, decimal.Decimal, decimal.Decimal(v))
+    assert m.dumps(decimal.Decimal("NaN")) == m.dumps("NaN")
+    # Negative zero is written as the string, since the mantissa has no sign.
+    for v in ["-0", "-0.000"]:
+        assert m.dumps(decimal.Decimal(v)) == m.dumps(v)
+        assert str(m.loads_as(decimal.Decimal, m.dumps(decimal.Decimal(v)))) == v
+
+    u = uuid.uuid4()
+    assert m.dumps(u) == b"\xd8\x25\x50" + u.bytes
+    assert m.loads(m.dumps(u)) == u
+    assert m.loads_as(uuid.UUID, m.dumps(str(u))) == u
+
+    assert m.dumps(b"ab") == b"\x42ab"
+    assert m.loads(b"\x42ab") == b"ab"
+
+    # The unknown tags are ignored, e.g. URI (tag 32) and self-described CBOR.
+    assert m.loads_as(str, b"\xd8\x20\x61x") == "x"
+    assert m.loads_as(typing.List[int], b"\xd9\xd9\xf7\x82\x01\x02") == [1, 2]
+    assert m.loads_as(typing.Dict[str, int], b"\xd8\x20\xa1\x61x\x01") == {"x": 1}
+
+    @dataclass
+    class Event:
+        at: datetime.datetime
+
+    @dataclass
+    class Other:
+        name: str
+
+    # The union is decoded by the shape of the input, which keeps the tags.
+    e = Event(d)
+    assert m.loads_as(typing.Union[Event, Other], m.dumps(e)) == e
+
+    # The buffered input keeps the tags as well.
+    @dataclass
+    class Price:
+        at: datetime.datetime
+        value: decimal.Decimal
+        id: uuid.UUID
+
+    p = Price(d, dec, u)
+    assert m.loads_as(Price, m.dumps(p), collect_errors=True) == p
+
+    # The tags are not used by the formats called from the hooks.
+    @dataclass
+    class Nested:
+        x: decimal.Decimal = field(
+            metadata={
+                "perde_serialize_with": perde.json.dumps,
+                "perde_deserialize_with": lambda s: perde.json.loads_as(
+                    decimal.Decimal, s
+                ),
+            }
+        )
+
+    n = Nested(dec)
+    assert m.loads(m.dumps(n)) == {"x": '"273.15"'}
+    assert m.loads_as(Nested, m.dumps(n)) == n
+
+
+def test_bson_native_types():
+    @dataclass
+    class Doc:
+        d: datetime.datetime
+        x: decimal.Decimal
+        u: uuid.UUID
+        b: bytes
+
+    def element(ty, v):
+        return bytes([len(v) + 8, 0, 0, 0, ty]) + b"a\x00" + v + b"\x00"
+
+    utc = datetime.timezone.utc
+    d = datetime.datetime(2013, 3, 21, 20, 4, tzinfo=utc)
+    ms = bytes.fromhex("80078d8e3d010000")
+    assert perde.bson.dumps({"a": d}) == element(9, ms)
+    assert perde.bson.loads(element(9, ms)) == {"a": d}
+    assert perde.bson.loads(element(9, ms))["a"].tzinfo == utc
+
+    # Naive datetimes are assumed to be in UTC.
+    naive = datetime.datetime(2013, 3, 21, 20, 4)
+    assert perde.bson.dumps({"a": naive}) == element(9, ms)
+
+    # Other time zones are converted to UTC, and microseconds are truncated.
+    jst = datetime.timezone(datetime.timedelta(hours=9))
+    precise = datetime.datetime(2013, 3, 22, 5, 4, 0, 123456, tzinfo=jst)
+    loaded = perde.bson.loads(perde.bson.dumps({"a": precise}))["a"]
+    assert loaded == d.replace(microsecond=123000)
+    assert loaded.tzinfo == utc
+
+    u = uuid.uuid4()
+    assert perde.bson.dumps({"a": u}) == element(5, b"\x10\0\0\0\x04" + u.bytes)
+    assert perde.bson.dumps({"a": b"ab"}) == element(5, b"\x02\0\0\0\x00ab")
+
+    v = Doc(d, decimal.Decimal("-273.15"), u, b"ab")
+    assert perde.bson.loads_as(Doc, perde.bson.dumps(v)) == v
+    assert perde.bson.loads(perde.bson.dumps(v)) == v.__dict__
+
+    # Strings are accepted as well.
+    s = perde.bson.dumps({"d": str(d), "x": "-273.15", "u": str(u), "b": b"ab"})
+    assert perde.bson.loads_as(Doc, s) == Doc(d, v.x, u, b"ab")
+
+    @dataclass
+    class OptionalDoc:
+        d: typing.Optional[datetime.datetime]
+        x: typing.Optional[decimal.Decimal]
+        u: typing.Optional[uuid.UUID]
+        i: typing.Union[datetime.datetime, int]
+
+    for o in [OptionalDoc(d, v.x, u, d), OptionalDoc(None, None, None, 3)]:
+        assert perde.bson.loads_as(OptionalDoc, perde.bson.dumps(o)) == o
+
+    oid = bytes.fromhex("507f1f77bcf86cd799439011")
+    assert perde.bson.loads(element(7, oid)) == {"a": oid.hex()}
+
+    with pytest.raises(perde.bson.BsonError):
+        perde.bson.dumps([1, 2])
+
+    # The native types are not used by the formats called from the hooks.
+    @dataclass
+    class Nested:
+        x: decimal.Decimal = field(
+            metadata={"perde_serialize_with": perde.json.dumps}
+        )
+
+    s = perde.bson.dumps(Nested(v.x))
+    assert perde.bson.loads(s) == {"x": '"-273.15"'}
+
+
+def test_json_lines():
+    @dataclass
+    class Line:
+        a: int
+        b: str
+
+    lines = [Line(1, "x"), Line(2, "y")]
+    text = perde.json.dumps_lines(lines)
+    assert text == '{"a":1,"b":"x"}\n{"a":2,"b":"y"}\n'
+    assert perde.json.loads_as_lines(Line, text) == lines
+    assert perde.json.loads_as_lines(Line, text.encode()) == lines
+    assert perde.json.loads_as_lines(Line, text.rstrip("\n")) == lines
+    assert perde.json.loads_as_lines(Line, "") == []
+    assert perde.json.loads_as_lines(Line, "\n\r\n") == []
+    assert perde.json.dumps_lines([]) == ""
+
+    # Blank lines and CRLF are ignored as whitespace.
+    assert perde.json.loads_as_lines(Line, "\n" + text.replace("\n", "\r\n\n")) == lines
+
+    # The values may span lines, but can't share a line.
+    pretty = '{\n  "a": 1,\n  "b": "x"\n}\n{\n  "a": 2,\n  "b": "y"\n}\n'
+    assert perde.json.loads_as_lines(Line, pretty) == lines
+
+    with pytest.raises(perde.json.JsonError):
+        perde.json.loads_as_lines(Line, '{"a":1,"b":"x"}\n{"a":2')
+    with pytest.raises(perde.json.JsonError) as e:
+        perde.json.loads_as_lines(Line, '{"a":1,"b":"x"}\n{"a":2,"b":"y"} 3')
+    assert str(e.value) == "value at line 2: expected a newline before the value"
+    assert (e.value.line, e.value.column, e.value.offset) == (2, 17, 32)
+
+def test_bincode_positional():
+    class Color(enum.Enum):
+        RED = "red"
+        GREEN = "green"
+
+    @dataclass
+    class Point:
+        x: int
+        y: typing.Optional[int]
+        c: Color
+        t: typing.Tuple[int, bool]
+        u: typing.Union[int, str]
+
+    def i64(v):
+        return struct.pack("<q", v)
+
+    def u32(v):
+        return struct.pack("<I", v)
+
+    # The same as the structs and enums of Rust in `bincode::serialize`.
+    p = Point(1, None, Color.GREEN, (2, True), "a")
+    b = i64(1) + b"\x00" + u32(1) + i64(2) + b"\x01" + u32(1) + i64(1) + b"a"
+    assert perde.bincode.dumps(p) == b
+    assert perde.bincode.loads_as(Point, b) == p
+
+    p = Point(1, 3, Color.RED, (2, False), 4)
+    b = i64(1) + b"\x01" + i64(3) + u32(0) + i64(2) + b"\x00" + u32(0) + i64(4)
+    assert perde.bincode.dumps(p) == b
+    assert perde.bincode.loads_as(Point, b) == p
+
+    with pytest.raises(perde.bincode.BincodeError):
+        perde.bincode.loads(b)
+    with pytest.raises(perde.bincode.BincodeError):
+        perde.bincode.loads_as(Point, b + b"\x00")
+    with pytest.raises(perde.UnknownVariantError):
+        perde.bincode.loads_as(Color, u32(2))
+    with pytest.raises(perde.UnsupportedTypeError):
+        perde.bincode.loads_as(typing.Any, i64(1))
+
+    # The positional encoding is not used by the formats called from the hooks.
+    @dataclass
+    class Nested:
+        c: Color = field(metadata={"perde_serialize_with": perde.json.dumps})
+
+    assert perde.bincode.dumps(Nested(Color.GREEN)) == i64(7) + b'"GREEN"'
+
+
+def test_avro():
+    class Color(enum.Enum):
+        RED = "red"
+        GREEN = "green"
+
+    @dataclass
+    class Point:
+        x: int
+        y: typing.Optional[int]
+        c: Color
+        u: typing.Union[int, str]
+        d: datetime.date
+        m: decimal.Decimal
+
+    # The values of `long` are in the zig-zag encoding; `y` and `u` are unions.
+    p = Point(-1, None, Color.GREEN, "a", datetime.date(1970, 1, 2), decimal.Decimal(1))
+    b = b"\x01\x00\x02" + b"\x02\x02a" + b"\x02" + b"\x08\x3b\x9a\xca\x00"
+    assert perde.avro.dumps(p) == b
+    assert perde.avro.loads_as(Point, b) == p
+
+    dt = datetime.datetime(2021, 1, 1, 9, tzinfo=datetime.timezone.utc)
+    assert perde.avro.loads_as(datetime.datetime, perde.avro.dumps(dt)) == dt
+
+    assert json.loads(perde.avro.schema(Point)) == {
+        "type": "record",
+        "name": "Point",
+        "namespace": "test_simple",
+        "fields": [
+            {"name": "x", "type": "long"},
+            {"name": "y", "type": ["null", "long"], "default": None},
+            {
+                "name": "c",
+                "type": {
+                    "type": "enum",
+                    "name": "Color",
+                    "namespace": "test_simple",
+                    "symbols": ["RED", "GREEN"],
+                },
+            },
+            {"name": "u", "type": ["long", "string"]},
+            {"name": "d", "type": {"type": "int", "logicalType": "date"}},
+            {
+                "name": "m",
+                "type": {
+                    "type": "bytes",
+                    "logicalType": "decimal",
+                    "precision": 38,
+                    "scale": 9,
+                },
+            },
+        ],
+    }
+
+    ps = [p, Point(1, 3, Color.RED, 4, datetime.date(2000, 2, 29), decimal.Decimal(-1))]
+    c = perde.avro.dumps_container(Point, ps)
+    assert c.startswith(b"Obj\x01")
+    assert perde.avro.loads_container_as(Point, c) == ps
+
+    with pytest.raises(perde.avro.AvroError):
+        perde.avro.loads(b)
+    with pytest.raises(perde.avro.AvroError):
+        perde.avro.loads_as(Point, b + b"\x00")
+    with pytest.raises(perde.avro.EncodeError):
+        perde.avro.dumps(decimal.Decimal("1e-10"))
+    with pytest.raises(perde.UnsupportedTypeError):
+        perde.avro.schema(typing.Tuple[int, str])
+
+    # The types without Avro schemas are rejected by the encoder and the decoder too.
+    @dataclass
+    class Unsupported:
+        a: typing.Literal["a", "b"]
+
+    with pytest.raises(perde.UnsupportedTypeError) as e:
+        perde.avro.schema(Unsupported)
+    assert str(e.value) == "`Literal` is not supported in Avro"
+    with pytest.raises(perde.UnsupportedTypeError) as e:
+        perde.avro.dumps(Unsupported("a"))
+    assert str(e.value) == "`Literal` is not supported in Avro"
+    with pytest.raises(perde.UnsupportedTypeError):
+        perde.avro.loads_as(Unsupported, b"\x00")
+
+    with pytest.raises(perde.SchemaError) as e:
+        perde.avro.schema(typing.Dict[int, str])
+    assert str(e.value) == "the keys of maps must be `str` in Avro, got `int`"
+    with pytest.raises(perde.avro.EncodeError):
+        perde.avro.dumps_container(typing.Dict[int, str], [{1: "x"}])
+    with pytest.raises(perde.avro.EncodeError) as e:
+        perde.avro.dumps({1: "x"})
+    assert str(e.value) == "the keys of maps must be `str` in Avro, got `int`"
+    assert perde.avro.loads_as(typing.Dict[str, int], perde.avro.dumps({"x": 1})) == {
+        "x": 1
+    }
+
+    @perde.attr(rename_all="kebab-case")
+    @dataclass
+    class Kebab:
+        foo_bar: int
+
+    with pytest.raises(perde.SchemaError) as e:
+        perde.avro.schema(Kebab)
+    assert str(e.value) == "`foo-bar` is not a valid name in Avro"
+
+    # The logical types are not used by the formats called from the hooks.
+    @dataclass
+    class Nested:
+        d: datetime.date = field(metadata={"perde_serialize_with": perde.json.dumps})
+
+    assert perde.avro.dumps(Nested(datetime.date(1970, 1, 2))) == b'\x18"1970-01-02"'
+
+
+T = typing.TypeVar("T")
+
+
+@dataclass
+class Page(typing.Generic[T]):
+    items: typing.List[T]
+
+
+def test_avro_names():
+    @dataclass
+    class User:
+        name: str
+
+    @dataclass
+    class Order:
+        id: int
+
+    @dataclass
+    class Pages:
+        users: Page[User]
+        orders: Page[Order]
+        more: Page[User]
+
+    s = json.loads(perde.avro.schema(Pages))
+    users, orders, more = (f["type"] for f in s["fields"])
+    assert (users["name"], users["namespace"]) == ("Page_User", "test_simple")
+    assert (orders["name"], orders["namespace"]) == ("Page_Order", "test_simple")
+    assert more == "test_simple.Page_User"
+
+    v = Pages(Page([User("a")]), Page([Order(1)]), Page([]))
+    c = perde.avro.dumps_container(Pages, [v])
+    assert perde.avro.loads_container_as(Pages, c) == [v]
+
+    def other_user():
+        @dataclass
+        class User:
+            id: int
+
+        return User
+
+    @dataclass
+    class Conflict:
+        a: User
+        b: other_user()
+
+    with pytest.raises(perde.SchemaError) as e:
+        perde.avro.schema(Conflict)
+    assert str(e.value) == "different types have the same Avro name `test_simple.User`"
@@ -10,6 +10,7 @@ from perde import json as perde_json
 from perde import yaml as perde_yaml
 from perde import toml as perde_toml
 from perde import msgpack as perde_msgpack
+from perde import cbor as perde_cbor
 
 
 @dataclass
@@ -224,6 +225,7 @@ _FORMATS = [
     Format("yaml", "yaml", perde_yaml, str, perde_yaml.YamlError),
     Format("msgpack", "msgpack", perde_msgpack, bytes, perde_msgpack.MsgpackError),
     Format("toml", "toml", perde_toml, str, perde_toml.TomlError),
+    Format("cbor", "cbor", perde_cbor, bytes, perde_cbor.CborError),
 ]
 
 FORMATS = mark(_FORMATS)
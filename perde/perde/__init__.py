@@ -1,5 +1,20 @@
-from .perde import resolve  # type: ignore
-from .perde import json, toml, yaml, msgpack  # noqa: F401
+from .perde import resolve, register  # type: ignore # noqa: F401
+from .perde import (  # type: ignore # noqa: F401
+    Error,
+    SchemaError,
+    UnsupportedTypeError,
+    InvalidAttributeError,
+    DecodeError,
+    MissingFieldError,
+    UnknownFieldError,
+    InvalidTypeError,
+    UnknownVariantError,
+    ValidationError,
+    EncodeError,
+    SkippedVariantError,
+    UnionMismatchError,
+)
+from .perde import json, toml, yaml, msgpack, cbor, bson, bincode, avro  # noqa: F401
 import sys
 import enum
 
@@ -7,6 +22,10 @@ import enum
 # Workaround in https://github.com/PyO3/pyo3/issues/759
 sys.modules["perde.json"] = json
 sys.modules["perde.msgpack"] = msgpack
+sys.modules["perde.cbor"] = cbor
+sys.modules["perde.bson"] = bson
+sys.modules["perde.bincode"] = bincode
+sys.modules["perde.avro"] = avro
 sys.modules["perde.yaml"] = yaml
 sys.modules["perde.toml"] = toml
 
@@ -0,0 +1,255 @@
+use perde_core::{avro::with_logical_types, exception::Category, prelude::*};
+use pyo3::ffi::*;
+use serde::{de::DeserializeSeed, Serialize};
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+};
+
+pub mod de;
+pub mod schema;
+pub mod ser;
+
+/// The magic bytes of Object Container Files.
+const MAGIC: &[u8] = b"Obj\x01";
+
+/// The error of the Avro binary encoding.
+#[derive(Debug)]
+pub struct CodecError(String);
+
+impl CodecError {
+    pub fn new<T: ToString>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+impl serde::ser::Error for CodecError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::new(msg)
+    }
+}
+
+impl serde::de::Error for CodecError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self::new(msg)
+    }
+}
+
+fn loads_as<T>(seed: T, object: &ObjectRef) -> Result<Object>
+where
+    T: for<'de> DeserializeSeed<'de, Value = Object>,
+{
+    let b = object.as_bytes().context("invalid argument")?;
+    let mut de = de::Deserializer::new(b);
+    let obj = with_logical_types(|| seed.deserialize(&mut de))?;
+    if !de.is_empty() {
+        bail!("trailing bytes after the value");
+    }
+    Ok(obj)
+}
+
+fn loads(_: &ObjectRef) -> Result<Object> {
+    // The input has no types, so it can be decoded only with the schema.
+    bail!("Avro cannot be decoded without the type; use `loads_as` instead")
+}
+
+fn dumps(object: WithSchema) -> Result<Object> {
+    let mut ser = ser::Serializer::default();
+    with_logical_types(|| object.serialize(&mut ser))?;
+    Object::new_bytes(&ser.out)
+}
+
+fn sync_marker() -> [u8; 16] {
+    let mut sync = [0; 16];
+    for chunk in sync.chunks_mut(8) {
+        let hash = RandomState::new().build_hasher().finish();
+        chunk.copy_from_slice(&hash.to_le_bytes());
+    }
+    sync
+}
+
+/// Writes an Object Container File with all the objects in a single block.
+fn write_container(schema: &Schema, objects: &ObjectRef) -> Result<Vec<u8>> {
+    let avsc = schema::to_avro(schema)?.to_string();
+
+    let mut block = ser::Serializer::default();
+    let mut count = 0;
+    for obj in objects.get_iter()? {
+        let obj = obj?;
+        with_logical_types(|| obj.with_schema(schema).serialize(&mut block))?;
+        count += 1;
+    }
+
+    let sync = sync_marker();
+    let mut out = MAGIC.to_vec();
+    ser::write_long(&mut out, 2);
+    ser::write_bytes(&mut out, b"avro.schema");
+    ser::write_bytes(&mut out, avsc.as_bytes());
+    ser::write_bytes(&mut out, b"avro.codec");
+    ser::write_bytes(&mut out, b"null");
+    ser::write_long(&mut out, 0);
+    out.extend_from_slice(&sync);
+    if count > 0 {
+        ser::write_long(&mut out, count);
+        ser::write_bytes(&mut out, &block.out);
+        out.extend_from_slice(&sync);
+    }
+    Ok(out)
+}
+
+/// Reads the objects of an Object Container File.
+///
+/// The values are decoded with the given type rather than the schema in the file.
+fn read_container(schema: &Schema, b: &[u8]) -> Result<Object> {
+    let mut de = de::Deserializer::new(b);
+    if de.read(MAGIC.len()).ok() != Some(MAGIC) {
+        bail!("not an Avro object container file");
+    }
+    loop {
+        let len = de.read_block_len()?;
+        if len == 0 {
+            break;
+        }
+        for _ in 0..len {
+            let key = de.read_bytes()?;
+            let value = de.read_bytes()?;
+            if key == b"avro.codec" && value != b"null" {
+                bail!(
+                    "unsupported codec `{}`; only `null` is supported",
+                    String::from_utf8_lossy(value)
+                );
+            }
+        }
+    }
+    let sync = de.read(16)?;
+
+    let mut objects = vec![];
+    while !de.is_empty() {
+        let count = de.read_long()?;
+        let mut block = de::Deserializer::new(de.read_bytes()?);
+        for _ in 0..count {
+            objects.push(with_logical_types(|| schema.deserialize(&mut block))?);
+        }
+        if !block.is_empty() {
+            bail!("trailing bytes in the block");
+        }
+        if de.read(16)? != sync {
+            bail!("sync marker mismatch");
+        }
+    }
+
+    let mut list = Object::build_list(objects.len())?;
+    for (i, obj) in objects.into_iter().enumerate() {
+        list.set(i, obj);
+    }
+    Ok(list.build())
+}
+
+pub extern "C" fn _schema(
+    _self: *mut PyObject,
+    args: *const *mut PyObject,
+    nargs: Py_ssize_t,
+    kwnames: *mut PyObject,
+) -> *mut PyObject {
+    let inner = || {
+        let args = FastArgs::new(args, nargs, kwnames);
+
+        if args.num_args() != 1 {
+            bail_type_err!(
+                "schema() requires 1 positional argument but got {}",
+                args.num_args()
+            );
+        }
+
+        let schema = args.arg(0)?.resolve(None).context("invalid argument")?;
+        Object::new_str(&schema::to_avro(&schema)?.to_string())
+    };
+
+    match inner() {
+        Ok(p) => p.into_ptr(),
+        Err(e) => {
+            e.restore_as(exceptions(), Category::Schema);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+pub extern "C" fn _dumps_container(
+    _self: *mut PyObject,
+    args: *const *mut PyObject,
+    nargs: Py_ssize_t,
+    kwnames: *mut PyObject,
+) -> *mut PyObject {
+    let inner = || {
+        let args = FastArgs::new(args, nargs, kwnames);
+
+        if args.num_args() != 2 {
+            bail_type_err!(
+                "dumps_container() requires 2 positional arguments but got {}",
+                args.num_args()
+            );
+        }
+
+        let schema = args.arg(0)?.resolve(None).context("invalid argument")?;
+        let b = perde_core::error::classify(|| write_container(&schema, args.arg(1)?))?;
+        Object::new_bytes(&b)
+    };
+
+    match perde_core::mode::isolated(inner) {
+        Ok(p) => p.into_ptr(),
+        Err(e) => {
+            e.restore_as(exceptions(), Category::Encode);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+pub extern "C" fn _loads_container_as(
+    _self: *mut PyObject,
+    args: *const *mut PyObject,
+    nargs: Py_ssize_t,
+    kwnames: *mut PyObject,
+) -> *mut PyObject {
+    let inner = || {
+        let args = FastArgs::new(args, nargs, kwnames);
+
+        if args.num_args() != 2 {
+            bail_type_err!(
+                "loads_container_as() requires 2 positional arguments but got {}",
+                args.num_args()
+            );
+        }
+
+        let schema = args.arg(0)?.resolve(None).context("invalid argument")?;
+        let b = args.arg(1)?.as_bytes().context("invalid argument")?;
+        perde_core::path::track(false, || read_container(&schema, b))
+    };
+
+    match perde_core::mode::isolated(inner) {
+        Ok(p) => p.into_ptr(),
+        Err(e) => {
+            e.restore_as(exceptions(), Category::Decode);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+impl_default_methods! {
+    avro,
+    AvroError,
+    loads_as,
+    loads,
+    dumps,
+    method_fastcall!(_schema, "schema", ""),
+    method_fastcall!(_dumps_container, "dumps_container", ""),
+    method_fastcall!(_loads_container_as, "loads_container_as", "")
+}
@@ -0,0 +1,195 @@
+use perde_core::{
+    avro::{self, DECIMAL_PRECISION, DECIMAL_SCALE},
+    error::ErrorKind,
+    object::SyncObject,
+    positional,
+    prelude::*,
+    schema::{Class, Enum, Union},
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// Derives the Avro schema of a type.
+///
+/// The records and the enums are defined where they first appear and referred
+/// to by the full name afterwards, which allows recursive classes. The module
+/// defining the type is the namespace, and the type arguments of a generic class
+/// are appended to the name, e.g. `Page_User` for `Page[User]`.
+pub fn to_avro(schema: &Schema) -> Result<Value> {
+    Builder::default().build(schema)
+}
+
+/// The type of a named schema.
+#[derive(PartialEq)]
+enum Named<'a> {
+    Record(&'a Class),
+    Enum(&'a Enum),
+}
+
+#[derive(Default)]
+struct Builder<'a> {
+    defined: HashMap<String, Named<'a>>,
+}
+
+impl<'a> Builder<'a> {
+    fn build(&mut self, schema: &'a Schema) -> Result<Value> {
+        Ok(match schema {
+            Schema::Bool => json!("boolean"),
+            Schema::Int => json!("long"),
+            Schema::Float => json!("double"),
+            Schema::Str => json!("string"),
+            Schema::Bytes | Schema::ByteArray => json!("bytes"),
+            Schema::DateTime => json!({"type": "long", "logicalType": "timestamp-micros"}),
+            Schema::Date => json!({"type": "int", "logicalType": "date"}),
+            Schema::Time => json!({"type": "long", "logicalType": "time-micros"}),
+            Schema::Decimal => json!({
+                "type": "bytes",
+                "logicalType": "decimal",
+                "precision": DECIMAL_PRECISION,
+                "scale": DECIMAL_SCALE,
+            }),
+            Schema::Uuid => json!({"type": "string", "logicalType": "uuid"}),
+            Schema::List(l) => json!({"type": "array", "items": self.build(&l.value)?}),
+            Schema::Set(s) => json!({"type": "array", "items": self.build(&s.value)?}),
+            Schema::FrozenSet(s) => json!({"type": "array", "items": self.build(&s.value)?}),
+            Schema::Dict(d) => {
+                avro::check(schema)?;
+                json!({"type": "map", "values": self.build(&d.value)?})
+            }
+            Schema::Class(c) => self.record(c)?,
+            Schema::Enum(e) => self.enumeration(e)?,
+            Schema::Union(u) => self.union(u)?,
+            Schema::Converter(c) => self.build(&c.schema)?,
+            Schema::Ref(r) => self.build(r.resolve()?)?,
+            s => {
+                return Err(err!("`{}` is not supported in Avro", s.name())
+                    .with_kind(ErrorKind::UnsupportedType))
+            }
+        })
+    }
+
+    fn record(&mut self, c: &'a Class) -> Result<Value> {
+        if let Some((_, field)) = c.transparent_field() {
+            return self.build(&field.schema);
+        }
+        let name = generic_name(c.tag_name(), &c.args)?;
+        let namespace = avro::namespace(&c.ty)?;
+        let fullname = full_name(&namespace, &name)?;
+        if !self.define(&fullname, Named::Record(c))? {
+            return Ok(json!(fullname));
+        }
+        let mut fields = vec![];
+        for (key, field) in positional::fields(c)? {
+            if field.attr.serialize_with.is_some() || field.attr.deserialize_with.is_some() {
+                bail!(
+                    "field `{}` of `{}` has a custom serializer, which has no Avro schema",
+                    key,
+                    name
+                );
+            }
+            check_name(&field.rename)?;
+            let mut f = json!({
+                "name": field.rename,
+                "type": self.build(&field.schema)?,
+            });
+            if field.schema.is_optional() {
+                f["default"] = Value::Null;
+            }
+            fields.push(f);
+        }
+        Ok(json!({
+            "type": "record",
+            "name": name,
+            "namespace": namespace,
+            "fields": fields,
+        }))
+    }
+
+    fn enumeration(&mut self, e: &'a Enum) -> Result<Value> {
+        let name = e.attr.rename.as_deref().unwrap_or(&e.name);
+        let namespace = avro::namespace(&e.object)?;
+        let fullname = full_name(&namespace, name)?;
+        if !self.define(&fullname, Named::Enum(e))? {
+            return Ok(json!(fullname));
+        }
+        let mut symbols = vec![];
+        for v in &e.variants {
+            check_name(&v.sername)?;
+            symbols.push(v.sername.as_str());
+        }
+        Ok(json!({
+            "type": "enum",
+            "name": name,
+            "namespace": namespace,
+            "symbols": symbols,
+        }))
+    }
+
+    /// Returns `true` if the name is defined for the first time, or `false` if
+    /// the same type is already defined.
+    fn define(&mut self, fullname: &str, named: Named<'a>) -> Result<bool> {
+        match self.defined.get(fullname) {
+            Some(d) if *d == named => Ok(false),
+            Some(_) => bail!("different types have the same Avro name `{}`", fullname),
+            None => {
+                self.defined.insert(fullname.into(), named);
+                Ok(true)
+            }
+        }
+    }
+
+    fn union(&mut self, u: &'a Union) -> Result<Value> {
+        // `null` comes first so that it can be the default of optional fields.
+        let mut branches = vec![];
+        if u.optional {
+            branches.push(json!("null"));
+        }
+        for v in &u.variants {
+            branches.push(self.build(v)?);
+        }
+        Ok(Value::Array(branches))
+    }
+}
+
+/// Returns the name of a generic class followed by the names of the type arguments.
+fn generic_name(name: &str, args: &[SyncObject]) -> Result<String> {
+    let mut name = name.to_string();
+    for arg in args {
+        name.push('_');
+        name.push_str(&arg_name(&*arg.resolve(None)?)?);
+    }
+    Ok(name)
+}
+
+fn arg_name(schema: &Schema) -> Result<String> {
+    Ok(match schema {
+        Schema::Class(c) => generic_name(c.tag_name(), &c.args)?,
+        Schema::Enum(e) => e.attr.rename.as_deref().unwrap_or(&e.name).into(),
+        Schema::Converter(c) => arg_name(&c.schema)?,
+        Schema::Ref(r) => arg_name(r.resolve()?)?,
+        s => s
+            .name()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect(),
+    })
+}
+
+fn full_name(namespace: &str, name: &str) -> Result<String> {
+    check_name(name)?;
+    for part in namespace.split('.') {
+        check_name(part)?;
+    }
+    Ok(format!("{}.{}", namespace, name))
+}
+
+/// Checks if the name matches `[A-Za-z_][A-Za-z0-9_]*` as required by Avro.
+fn check_name(name: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("`{}` is not a valid name in Avro", name);
+    }
+    Ok(())
+}
@@ -0,0 +1,283 @@
+use super::CodecError as Error;
+use serde::ser::{self, Serialize};
+
+/// Writes a `long`, i.e. the variable-length zig-zag encoding of a signed integer.
+pub fn write_long(out: &mut Vec<u8>, v: i64) {
+    let mut n = ((v << 1) ^ (v >> 63)) as u64;
+    while n >= 0x80 {
+        out.push(n as u8 | 0x80);
+        n >>= 7;
+    }
+    out.push(n as u8);
+}
+
+/// Writes `bytes` or `string`, i.e. the length followed by the data.
+pub fn write_bytes(out: &mut Vec<u8>, v: &[u8]) {
+    write_long(out, v.len() as i64);
+    out.extend_from_slice(v);
+}
+
+/// Serializes a value in the Avro binary encoding.
+///
+/// The records are written as tuples of the fields, the enums and the branches
+/// of unions as the indices, so the values are expected to be given by position.
+#[derive(Default)]
+pub struct Serializer {
+    pub out: Vec<u8>,
+}
+
+impl Serializer {
+    fn block(&mut self, len: Option<usize>) -> Result<&mut Self, Error> {
+        let len = len.ok_or_else(|| Error::new("the length of arrays and maps must be known"))?;
+        if len > 0 {
+            write_long(&mut self.out, len as i64);
+        }
+        Ok(self)
+    }
+}
+
+/// Writes the items of an array or a map, followed by the end of the blocks.
+pub struct Block<'a>(&'a mut Serializer);
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Block<'a>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Block<'a>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.out.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        write_long(&mut self.out, v);
+        Ok(())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        if v > std::i64::MAX as u64 {
+            return Err(Error::new(format!(
+                "integer `{}` is out of range of long",
+                v
+            )));
+        }
+        self.serialize_i64(v as i64)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.out.extend_from_slice(&v.to_le_bytes());
+        Ok(())
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        write_bytes(&mut self.out, v.as_bytes());
+        Ok(())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        write_bytes(&mut self.out, v);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(Error::new("`None` must be a branch of a union"))
+    }
+
+    fn serialize_some<T>(self, _: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(Error::new("optional values must be a branch of a union"))
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+    ) -> Result<(), Error> {
+        self.serialize_u32(index)
+    }
+
+    fn serialize_newtype_struct<T>(self, _: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        write_long(&mut self.out, index as i64);
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Block<'a>, Error> {
+        Ok(Block(self.block(len)?))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, Error> {
+        write_long(&mut self.out, index as i64);
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Block<'a>, Error> {
+        Ok(Block(self.block(len)?))
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        index: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self, Error> {
+        write_long(&mut self.out, index as i64);
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<'a> ser::SerializeSeq for Block<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.0)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        write_long(&mut self.0.out, 0);
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeMap for Block<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        key.serialize(&mut *self.0)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.0)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        write_long(&mut self.0.out, 0);
+        Ok(())
+    }
+}
+
+macro_rules! impl_sequential {
+    ($trait:ident, $method:ident $(, $key:ty)?) => {
+        impl<'a> ser::$trait for &'a mut Serializer {
+            type Ok = ();
+            type Error = Error;
+
+            fn $method<T>(&mut self, $(_: $key,)? value: &T) -> Result<(), Error>
+            where
+                T: ?Sized + Serialize,
+            {
+                value.serialize(&mut **self)
+            }
+
+            fn end(self) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+    };
+}
+
+impl_sequential!(SerializeTuple, serialize_element);
+impl_sequential!(SerializeTupleStruct, serialize_field);
+impl_sequential!(SerializeTupleVariant, serialize_field);
+impl_sequential!(SerializeStruct, serialize_field, &'static str);
+impl_sequential!(SerializeStructVariant, serialize_field, &'static str);
@@ -0,0 +1,33 @@
+use ::bincode::Options;
+use perde_core::{positional::with_positional, prelude::*};
+use serde::de::DeserializeSeed;
+
+/// The options of `bincode::serialize`, except that trailing bytes are rejected.
+fn options() -> impl Options {
+    ::bincode::options().with_fixint_encoding()
+}
+
+fn loads_as<T>(seed: T, object: &ObjectRef) -> Result<Object>
+where
+    T: for<'de> DeserializeSeed<'de, Value = Object>,
+{
+    let b = object.as_bytes().context("invalid argument")?;
+    Ok(with_positional(|| options().deserialize_seed(seed, b))?)
+}
+
+fn loads(_: &ObjectRef) -> Result<Object> {
+    // The input has no types, so it can be decoded only with the schema.
+    bail!("bincode cannot be decoded without the type; use `loads_as` instead")
+}
+
+fn dumps(object: WithSchema) -> Result<Object> {
+    Object::new_bytes(&with_positional(|| options().serialize(&object))?)
+}
+
+impl_default_methods! {
+    bincode,
+    BincodeError,
+    loads_as,
+    loads,
+    dumps
+}
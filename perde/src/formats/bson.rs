@@ -0,0 +1,34 @@
+use ::bson::{Bson, Deserializer, Document};
+use perde_core::{bson::with_native_types, prelude::*};
+use serde::{de::DeserializeSeed, Deserialize};
+
+fn read_document(object: &ObjectRef) -> Result<Deserializer> {
+    let mut b = object.as_bytes().context("invalid argument")?;
+    let doc = Document::from_reader(&mut b)?;
+    Ok(Deserializer::new(Bson::Document(doc)))
+}
+
+fn loads_as<T>(seed: T, object: &ObjectRef) -> Result<Object>
+where
+    T: for<'de> DeserializeSeed<'de, Value = Object>,
+{
+    let de = read_document(object)?;
+    Ok(with_native_types(|| seed.deserialize(de))?)
+}
+
+fn loads(object: &ObjectRef) -> Result<Object> {
+    let de = read_document(object)?;
+    Ok(with_native_types(|| Object::deserialize(de))?)
+}
+
+fn dumps(object: WithSchema) -> Result<Object> {
+    Object::new_bytes(&with_native_types(|| ::bson::to_vec(&object))?)
+}
+
+impl_default_methods! {
+    bson,
+    BsonError,
+    loads_as,
+    loads,
+    dumps
+}
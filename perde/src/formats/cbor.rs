@@ -0,0 +1,30 @@
+use perde_core::{cbor::with_tags, prelude::*};
+use serde::de::DeserializeSeed;
+
+fn loads_as<T>(seed: T, object: &ObjectRef) -> Result<Object>
+where
+    T: for<'de> DeserializeSeed<'de, Value = Object>,
+{
+    let b = object.as_bytes().context("invalid argument")?;
+    let mut de = serde_cbor::Deserializer::from_slice(b);
+    let obj = with_tags(|| seed.deserialize(&mut de))?;
+    de.end()?;
+    Ok(obj)
+}
+
+fn loads(object: &ObjectRef) -> Result<Object> {
+    let b = object.as_bytes().context("invalid argument")?;
+    Ok(serde_cbor::from_slice(b)?)
+}
+
+fn dumps(object: WithSchema) -> Result<Object> {
+    Object::new_bytes(&with_tags(|| serde_cbor::to_vec(&object))?)
+}
+
+impl_default_methods! {
+    cbor,
+    CborError,
+    loads_as,
+    loads,
+    dumps
+}
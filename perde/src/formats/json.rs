@@ -1,25 +1,194 @@
-use perde_core::prelude::*;
-use serde::de::DeserializeSeed;
+use perde_core::{
+    exception::Category,
+    path::{Locate, Segment},
+    prelude::*,
+};
+use pyo3::ffi::*;
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer};
+use std::{cell::RefCell, rc::Rc};
 
-fn loads_as(schema: &Schema, object: &ObjectRef) -> Result<Object> {
+fn loads_as<T>(seed: T, object: &ObjectRef) -> Result<Object>
+where
+    T: for<'de> DeserializeSeed<'de, Value = Object>,
+{
     let s = object.as_str().context("invalid argument")?;
     let mut de = serde_json::Deserializer::from_str(&s);
-    Ok(schema.deserialize(&mut de)?)
+    seed.deserialize(&mut de).map_err(|e| error(s, e))
 }
 
 fn loads(object: &ObjectRef) -> Result<Object> {
     let s = object.as_str().context("invalid argument")?;
-    Ok(serde_json::from_str(s)?)
+    serde_json::from_str(s).map_err(|e| error(s, e))
+}
+
+fn error(input: &str, e: serde_json::Error) -> Error {
+    // The line number is zero if the position is unknown.
+    if e.line() == 0 {
+        Error::new(e)
+    } else {
+        let position = Position::from_line_column(input, e.line(), e.column());
+        Error::new(e).with_position(position)
+    }
 }
 
 fn dumps(object: WithSchema) -> Result<Object> {
     Object::new_str(&serde_json::to_string(&object)?)
 }
 
+thread_local! {
+    // The schema of the values decoded by `Line`, because `StreamDeserializer`
+    // decodes the types implementing `Deserialize` rather than the seeds.
+    static LINE_SCHEMA: RefCell<Option<Rc<Schema>>> = RefCell::new(None);
+}
+
+/// A value of JSON Lines decoded with the schema in `LINE_SCHEMA`.
+struct Line(Object);
+
+impl<'de> Deserialize<'de> for Line {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match LINE_SCHEMA.with(|s| s.borrow().clone()) {
+            Some(schema) => schema.as_ref().deserialize(deserializer).map(Line),
+            None => Err(de::Error::custom("the schema of the lines is not set")),
+        }
+    }
+}
+
+/// Decodes the JSON Lines, i.e. one value per line, into a list.
+///
+/// The values are decoded in turn by `StreamDeserializer`, so the errors report
+/// the position in the whole input, and the line where the failing value starts.
+/// A value may span lines and blank lines are ignored, but each value must start
+/// on a new line.
+fn loads_as_lines(schema: &Schema, s: &str) -> Result<Object> {
+    let prev = LINE_SCHEMA.with(|l| l.replace(Some(Rc::new(schema.clone()))));
+    let res = read_lines(s);
+    LINE_SCHEMA.with(|l| l.replace(prev));
+    res
+}
+
+fn read_lines(s: &str) -> Result<Object> {
+    let mut objects = vec![];
+    let mut stream = serde_json::Deserializer::from_str(s).into_iter::<Line>();
+    loop {
+        let end = stream.byte_offset();
+        let start = s.len() - s[end..].trim_start().len();
+        if end > 0 && start < s.len() && !s[end..start].contains('\n') {
+            let line = s[..start].matches('\n').count() + 1;
+            let column = start - s[..start].rfind('\n').map_or(0, |i| i + 1) + 1;
+            return Err(err!("expected a newline before the value")
+                .with_position(Position::from_line_column(s, line, column)))
+            .context(format_args!("value at line {}", line));
+        }
+        let i = objects.len();
+        match stream.next().map(|res| res.at(|| Segment::Index(i))) {
+            Some(Ok(Line(obj))) => objects.push(obj),
+            // The offset is at the start of the failing value.
+            Some(Err(e)) => {
+                let line = line_of(s, stream.byte_offset());
+                return Err(error(s, e)).context(format_args!("value at line {}", line));
+            }
+            None => break,
+        }
+    }
+
+    let mut list = Object::build_list(objects.len())?;
+    for (i, obj) in objects.into_iter().enumerate() {
+        list.set(i, obj);
+    }
+    Ok(list.build())
+}
+
+/// Returns the line number of the value starting at the byte offset.
+fn line_of(s: &str, offset: usize) -> usize {
+    // The offset is before the whitespace preceding the value.
+    let ws = s[offset..].len() - s[offset..].trim_start().len();
+    s[..offset + ws].matches('\n').count() + 1
+}
+
+fn dumps_lines(objects: &ObjectRef) -> Result<Object> {
+    let mut out = String::new();
+    for obj in objects.get_iter()? {
+        let obj = obj?;
+        out.push_str(&serde_json::to_string(&obj.resolved_object()?)?);
+        out.push('\n');
+    }
+    Object::new_str(&out)
+}
+
+pub extern "C" fn _loads_as_lines(
+    _self: *mut PyObject,
+    args: *const *mut PyObject,
+    nargs: Py_ssize_t,
+    kwnames: *mut PyObject,
+) -> *mut PyObject {
+    let inner = || {
+        let args = FastArgs::new(args, nargs, kwnames);
+
+        if args.num_args() != 2 {
+            bail_type_err!(
+                "loads_as_lines() requires 2 positional arguments but got {}",
+                args.num_args()
+            );
+        }
+
+        let schema = args.arg(0)?.resolve(None).context("invalid argument")?;
+        let input = args.arg(1)?;
+        let s = if input.get_type()?.is_bytes() {
+            std::str::from_utf8(input.as_bytes()?)
+                .map_err(Error::new)
+                .context("invalid argument")?
+        } else {
+            input.as_str().context("invalid argument")?
+        };
+        perde_core::path::track(false, || loads_as_lines(&schema, s))
+    };
+
+    match perde_core::mode::isolated(inner) {
+        Ok(p) => p.into_ptr(),
+        Err(e) => {
+            e.restore_as(exceptions(), Category::Decode);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+pub extern "C" fn _dumps_lines(
+    _self: *mut PyObject,
+    args: *const *mut PyObject,
+    nargs: Py_ssize_t,
+    kwnames: *mut PyObject,
+) -> *mut PyObject {
+    let inner = || {
+        let args = FastArgs::new(args, nargs, kwnames);
+
+        if args.num_args() != 1 {
+            bail_type_err!(
+                "dumps_lines() requires 1 positional argument but got {}",
+                args.num_args()
+            );
+        }
+
+        perde_core::error::classify(|| dumps_lines(args.arg(0)?))
+    };
+
+    match perde_core::mode::isolated(inner) {
+        Ok(p) => p.into_ptr(),
+        Err(e) => {
+            e.restore_as(exceptions(), Category::Encode);
+            std::ptr::null_mut()
+        }
+    }
+}
+
 impl_default_methods! {
     json,
     JsonError,
     loads_as,
     loads,
-    dumps
+    dumps,
+    method_fastcall!(_loads_as_lines, "loads_as_lines", ""),
+    method_fastcall!(_dumps_lines, "dumps_lines", "")
 }
@@ -1,3 +1,7 @@
+pub mod avro;
+pub mod bincode;
+pub mod bson;
+pub mod cbor;
 pub mod json;
 pub mod msgpack;
 pub mod toml;
@@ -1,10 +1,13 @@
 use perde_core::prelude::*;
 use serde::de::DeserializeSeed;
 
-fn loads_as(schema: &Schema, object: &ObjectRef) -> Result<Object> {
+fn loads_as<T>(seed: T, object: &ObjectRef) -> Result<Object>
+where
+    T: for<'de> DeserializeSeed<'de, Value = Object>,
+{
     let b = object.as_bytes().context("invalid argument")?;
     let mut de = rmp_serde::Deserializer::from_read_ref(&b);
-    Ok(schema.deserialize(&mut de)?)
+    Ok(seed.deserialize(&mut de)?)
 }
 
 fn loads(object: &ObjectRef) -> Result<Object> {
@@ -1,15 +1,30 @@
 use perde_core::prelude::*;
 use serde::de::DeserializeSeed;
 
-fn loads_as(schema: &Schema, object: &ObjectRef) -> Result<Object> {
+fn loads_as<T>(seed: T, object: &ObjectRef) -> Result<Object>
+where
+    T: for<'de> DeserializeSeed<'de, Value = Object>,
+{
     let buf = object.as_str().context("invalid argument")?;
     let mut deserializer = toml::de::Deserializer::new(&buf);
-    Ok(schema.deserialize(&mut deserializer)?)
+    seed.deserialize(&mut deserializer)
+        .map_err(|e| error(buf, e))
 }
 
 fn loads(object: &ObjectRef) -> Result<Object> {
     let s = object.as_str().context("invalid argument")?;
-    Ok(toml::from_str(&s)?)
+    toml::from_str(&s).map_err(|e| error(s, e))
+}
+
+fn error(input: &str, e: toml::de::Error) -> Error {
+    // The line and column are zero-based.
+    match e.line_col() {
+        Some((line, col)) => {
+            let position = Position::from_line_column(input, line + 1, col + 1);
+            Error::new(e).with_position(position)
+        }
+        None => Error::new(e),
+    }
 }
 
 fn dumps(object: WithSchema) -> Result<Object> {
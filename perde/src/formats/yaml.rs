@@ -1,13 +1,27 @@
 use perde_core::prelude::*;
+use serde::de::DeserializeSeed;
 
-fn loads_as(schema: &Schema, object: &ObjectRef) -> Result<Object> {
+fn loads_as<T>(seed: T, object: &ObjectRef) -> Result<Object>
+where
+    T: for<'de> DeserializeSeed<'de, Value = Object>,
+{
     let s = object.as_str().context("invalid argument")?;
-    Ok(serde_yaml::seed::from_str_seed(s, schema)?)
+    serde_yaml::seed::from_str_seed(s, seed).map_err(|e| error(s, e))
 }
 
 fn loads(object: &ObjectRef) -> Result<Object> {
     let s = object.as_str().context("invalid argument")?;
-    Ok(serde_yaml::from_str(s)?)
+    serde_yaml::from_str(s).map_err(|e| error(s, e))
+}
+
+fn error(input: &str, e: serde_yaml::Error) -> Error {
+    match e.location() {
+        Some(l) => {
+            let position = Position::from_char_index(input, l.line(), l.column(), l.index());
+            Error::new(e).with_position(position)
+        }
+        None => Error::new(e),
+    }
 }
 
 fn dumps(object: WithSchema) -> Result<Object> {
@@ -1,12 +1,12 @@
-use crate::formats::{json::*, msgpack::*, toml::*, yaml::*};
-use perde_core::prelude::*;
+use crate::formats::{
+    avro::*, bincode::*, bson::*, cbor::*, json::*, msgpack::*, toml::*, yaml::*,
+};
+use perde_core::{exception::Category, prelude::*};
 use pyo3::ffi::*;
 use std::collections::HashMap;
 
 mod formats;
 
-pyo3::create_exception!(perde, Error, pyo3::exceptions::PyException);
-
 pub extern "C" fn resolve(
     _self: *mut pyo3::ffi::PyObject,
     args: *const *mut pyo3::ffi::PyObject,
@@ -44,7 +44,56 @@ pub extern "C" fn resolve(
     match inner() {
         Ok(p) => p.into_ptr(),
         Err(e) => {
-            e.restore_as::<Error>();
+            let exceptions =
+                perde_core::exception::perde().expect("exception types are created on import");
+            e.restore_as(exceptions, Category::Schema);
+            std::ptr::null_mut()
+        }
+    }
+}
+
+pub extern "C" fn register(
+    _self: *mut pyo3::ffi::PyObject,
+    args: *const *mut pyo3::ffi::PyObject,
+    nargs: Py_ssize_t,
+    kwnames: *mut pyo3::ffi::PyObject,
+) -> *mut pyo3::ffi::PyObject {
+    let inner = || {
+        let args = FastArgs::new(args, nargs, kwnames);
+
+        if args.num_args() != 3 {
+            bail_type_err!(
+                "register() requires 3 positional arguments but got {}",
+                args.num_args()
+            );
+        }
+
+        let mut as_type = None;
+        if let Some(iter) = args.iter_kwargs()? {
+            for res in iter {
+                let (key, value) = res?;
+                match key {
+                    "as_type" => as_type = Some(value),
+                    _ => bail_type_err!("register() got an unexpected keyword argument `{}`", key),
+                }
+            }
+        }
+        let as_type = match as_type {
+            Some(as_type) => as_type,
+            None => bail_type_err!("register() requires keyword argument `as_type`"),
+        };
+
+        args.arg(0)?.register(args.arg(1)?, args.arg(2)?, as_type)?;
+
+        Ok(Object::new_none())
+    };
+
+    match inner() {
+        Ok(p) => p.into_ptr(),
+        Err(e) => {
+            let exceptions =
+                perde_core::exception::perde().expect("exception types are created on import");
+            e.restore_as(exceptions, Category::Schema);
             std::ptr::null_mut()
         }
     }
@@ -56,6 +105,11 @@ module!(
     add_submodule!(toml),
     add_submodule!(yaml),
     add_submodule!(msgpack),
-    exception!(Error),
-    method_fastcall!(resolve, "resolve", "")
+    add_submodule!(cbor),
+    add_submodule!(bson),
+    add_submodule!(bincode),
+    add_submodule!(avro),
+    exceptions!(perde_core::exception::perde()),
+    method_fastcall!(resolve, "resolve", ""),
+    method_fastcall!(register, "register", "")
 );